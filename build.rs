@@ -0,0 +1,105 @@
+//! Compiles `llre.cfg` into the LL(1) table of the regex grammar, so the
+//! binary does not need the grammar file at runtime. See `src/llre.rs`.
+
+// The modules are checked as part of the library, this only uses a few of them.
+#![allow(unused, clippy::all)]
+
+#[path = "src/cfg.rs"]
+mod cfg;
+#[path = "src/cfg_parser.rs"]
+mod cfg_parser;
+#[path = "src/codegen.rs"]
+mod codegen;
+#[path = "src/ebnf.rs"]
+mod ebnf;
+#[path = "src/error.rs"]
+mod error;
+#[path = "src/ll_table.rs"]
+mod ll_table;
+#[path = "src/production.rs"]
+mod production;
+#[path = "src/symbol.rs"]
+mod symbol;
+
+use cfg::CFG;
+use ll_table::LLTable;
+use symbol::Symbol;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    for file in &[
+        "llre.cfg",
+        "src/cfg.rs",
+        "src/cfg_parser.rs",
+        "src/codegen.rs",
+        "src/ebnf.rs",
+        "src/ll_table.rs",
+        "src/production.rs",
+        "src/symbol.rs",
+    ] {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+
+    let cfg = CFG::from_file("llre.cfg").unwrap();
+    let table = LLTable::from_cfg(&cfg);
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "use super::GeneratedSymbol::{{Lambda, NonTerminal, Terminal}};\n\n\
+         pub type Production = (&'static str, &'static [super::GeneratedSymbol], Option<&'static str>);\n\
+         pub type Row = (&'static str, &'static [(&'static str, Option<usize>)]);\n"
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        "pub const START: &str = {:?};\n",
+        cfg.start_symbol.non_terminal()
+    )
+    .unwrap();
+
+    writeln!(out, "pub const PRODUCTIONS: &[Production] = &[").unwrap();
+    for (idx, production) in cfg.productions.iter().enumerate() {
+        let rhs: Vec<String> = production
+            .symbols()
+            .iter()
+            .map(|s| match s {
+                Symbol::Terminal(t) => format!("Terminal({:?})", t.terminal()),
+                Symbol::NonTerminal(nt) => format!("NonTerminal({:?})", nt.non_terminal()),
+                Symbol::Lambda => String::from("Lambda"),
+            })
+            .collect();
+        let lhs = cfg.lhs(idx).unwrap().non_terminal();
+        let action = cfg.actions.get(&idx).map(String::as_str);
+        writeln!(out, "    ({:?}, &[{}], {:?}),", lhs, rhs.join(", "), action).unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(out, "pub const TABLE: &[Row] = &[").unwrap();
+    for (nt, row) in &table.table {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|(t, cell)| format!("({:?}, {:?})", t.terminal(), cell))
+            .collect();
+        writeln!(
+            out,
+            "    ({:?}, &[{}]),",
+            nt.non_terminal(),
+            cells.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("llre_table.rs"), out).unwrap();
+
+    // Compiled by the codegen tests.
+    let mut parser = Vec::new();
+    codegen::write_parser(&cfg, &table, &mut parser).unwrap();
+    fs::write(Path::new(&out_dir).join("llre_parser.rs"), parser).unwrap();
+}
@@ -13,6 +13,8 @@ pub enum TokenKind {
     Dash,
     Pipe,
     Char,
+    /// Input that is not a token, with a message in `Token::data`.
+    Error,
 }
 
 impl fmt::Display for TokenKind {
@@ -26,6 +28,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Dash => "dash",
             TokenKind::Char => "char",
             TokenKind::Pipe => "pipe",
+            TokenKind::Error => "error",
         };
 
         write!(f, "{}", val)
@@ -68,6 +71,10 @@ impl<'f> Lexer<'f> {
                 Some('n') => Token::new(TokenKind::Char, "x0a"),
                 Some('s') => Token::new(TokenKind::Char, "x20"),
                 Some('\\') => Token::new(TokenKind::Char, "\\"),
+                Some('x') if self.peek() == Some('{') => match self.code_point() {
+                    Ok(c) => Token::new(TokenKind::Char, c),
+                    Err(message) => Token::new(TokenKind::Error, message),
+                },
                 Some(c) => Token::new(TokenKind::Char, c),
                 None => panic!("Backslash characters must escape another character."),
             },
@@ -76,6 +83,25 @@ impl<'f> Lexer<'f> {
         })
     }
 
+    /// Reads the `{H...}` of a `\x{H...}` escape.
+    fn code_point(&mut self) -> Result<char, String> {
+        self.eat();
+        let mut hex = String::new();
+
+        loop {
+            match self.eat() {
+                Some('}') => break,
+                Some(c) => hex.push(c),
+                None => return Err(format!("Unterminated escape \\x{{{}", hex)),
+            }
+        }
+
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| format!("\\x{{{}}} is not a Unicode scalar value", hex))
+    }
+
     pub fn iter(self) -> impl Iterator<Item = Token> + 'f {
         LexerIter { inner: self }
     }
@@ -2,20 +2,55 @@
 pub mod alphabet_translator {
     #[inline(always)]
     pub fn hex_to_char(hex: &str) -> char {
-        let numeric_code = u8::from_str_radix(&hex, 16).unwrap();
-        numeric_code as char
+        let hex = hex.trim_start_matches('{').trim_end_matches('}');
+        let numeric_code = u32::from_str_radix(hex, 16).unwrap();
+        std::char::from_u32(numeric_code).unwrap()
     }
 
+    /// `xHH` for the first 256 code points and `x{H...}` for the rest.
     pub fn char_to_hex(c: char) -> String {
-        let mut temp = String::new();
-        let n = c as u8;
-        let mut hex_code = format!("{:X}", n);
-        if hex_code.len() == 1 {
-            hex_code.insert(0, '0');
+        if (c as u32) < 0x100 {
+            format!("x{:02X}", c as u32)
+        } else {
+            format!("x{{{:X}}}", c as u32)
         }
-        temp.push('x');
-        temp.push_str(&hex_code);
-        temp
+    }
+
+    /// Decodes an alphabet or token where a character is written `xHH`,
+    /// `x{H...}` or as itself. An `x` that does not start either escape is
+    /// just an `x`.
+    pub fn decode(input: &str) -> Vec<char> {
+        let mut chars = Vec::new();
+        let mut rest = input;
+
+        while let Some(c) = rest.chars().next() {
+            let (decoded, len) = match c {
+                'x' => decode_escape(&rest[1..]).map_or((c, 1), |(c, len)| (c, len + 1)),
+                c => (c, c.len_utf8()),
+            };
+
+            chars.push(decoded);
+            rest = &rest[len..];
+        }
+
+        chars
+    }
+
+    /// The character and length of the `HH` or `{H...}` after an `x`.
+    fn decode_escape(input: &str) -> Option<(char, usize)> {
+        let (hex, len) = if input.starts_with('{') {
+            let end = input.find('}')?;
+            (&input[1..end], end + 1)
+        } else {
+            (input.get(..2)?, 2)
+        };
+
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let c = std::char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+        Some((c, len))
     }
 
     // TODO probably could do this with some sort of collect and map
@@ -27,9 +62,11 @@ pub mod alphabet_translator {
         temp
     }
 
+    /// Encodes the characters an `.nfa` file cannot hold as themselves:
+    /// whitespace, `\\` and anything outside of ASCII.
     pub fn char_to_hex_if_whitespace(c: char) -> String {
         // TODO are there more whitspaces?
-        if c.is_ascii_whitespace() || c == '\\' {
+        if c.is_whitespace() || c.is_control() || c == '\\' || !c.is_ascii() {
             return char_to_hex(c);
         }
         c.to_string()
@@ -98,4 +135,30 @@ mod test {
     fn wack_hex() {
         assert_eq!('\\', alphabet_translator::hex_to_char("5C"));
     }
+
+    #[test]
+    fn unicode() {
+        assert_eq!("x{1F600}", alphabet_translator::char_to_hex('😀'));
+        assert_eq!("xE9", alphabet_translator::char_to_hex('é'));
+        assert_eq!(
+            "x{3BB}",
+            alphabet_translator::char_to_hex_if_whitespace('λ')
+        );
+        assert_eq!('😀', alphabet_translator::hex_to_char("{1F600}"));
+    }
+
+    #[test]
+    fn decoding() {
+        let decode = |s| {
+            alphabet_translator::decode(s)
+                .into_iter()
+                .collect::<String>()
+        };
+
+        assert_eq!(decode("x0Ax20x5Cab"), "\n \\ab");
+        assert_eq!(decode("x{1F600}x{3bb}é"), "😀λé");
+        assert_eq!(decode("xyzx"), "xyzx");
+        assert_eq!(decode("x{}x{D800}x{zz}"), "x{}x{D800}x{zz}");
+        assert_eq!(decode("x2"), "x2");
+    }
 }
@@ -0,0 +1,611 @@
+use crate::cfg::CFG;
+use crate::ll_table::LLTable;
+use crate::symbol::{NonTerminal, Terminal};
+use anyhow::anyhow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    /// One `section,non_terminal,production,terminal` row per fact.
+    Csv,
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "text" => ReportFormat::Text,
+            "json" => ReportFormat::Json,
+            "csv" => ReportFormat::Csv,
+            "markdown" | "md" => ReportFormat::Markdown,
+            _ => return Err(anyhow!("Unknown report format: {:?}", s)),
+        })
+    }
+}
+
+/// Two or more productions of `non_terminal` predicted by the same terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict<'c> {
+    pub non_terminal: &'c NonTerminal,
+    pub terminal: Terminal,
+    pub productions: Vec<usize>,
+}
+
+/// The nullable, FIRST, FOLLOW and PREDICT sets of a grammar and its LL(1) table.
+#[derive(Debug, Clone)]
+pub struct GrammarAnalysis<'c> {
+    cfg: &'c CFG,
+    pub nullable: BTreeSet<NonTerminal>,
+    pub first: BTreeMap<&'c NonTerminal, BTreeSet<Terminal>>,
+    pub follow: BTreeMap<&'c NonTerminal, BTreeSet<Terminal>>,
+    /// Indexed by production.
+    pub predict: Vec<BTreeSet<Terminal>>,
+    pub table: LLTable<'c>,
+}
+
+impl<'c> GrammarAnalysis<'c> {
+    pub fn new(cfg: &'c CFG) -> Self {
+        let first = cfg
+            .non_terminals
+            .iter()
+            .map(|nt| (nt, cfg.first_set(nt)))
+            .collect();
+
+        let follow = cfg
+            .non_terminals
+            .iter()
+            .map(|nt| (nt, cfg.follow(nt, BTreeSet::new()).0))
+            .collect();
+
+        let predict = cfg
+            .productions
+            .iter()
+            .enumerate()
+            .map(|(idx, production)| cfg.predict_set(&lhs(cfg, idx), production))
+            .collect();
+
+        Self {
+            cfg,
+            nullable: cfg.nullable(),
+            first,
+            follow,
+            predict,
+            table: LLTable::from_cfg(cfg),
+        }
+    }
+
+    /// Table cells claimed by more than one production. `LLTable` keeps only
+    /// the last of them, so the grammar is LL(1) exactly when this is empty.
+    pub fn conflicts(&self) -> Vec<Conflict<'c>> {
+        let mut cells: BTreeMap<(&NonTerminal, &Terminal), Vec<usize>> = BTreeMap::new();
+
+        for (nt, indices) in &self.cfg.production_map {
+            for idx in indices {
+                for terminal in &self.predict[*idx] {
+                    cells.entry((nt, terminal)).or_default().push(*idx);
+                }
+            }
+        }
+
+        cells
+            .into_iter()
+            .filter(|(_, productions)| productions.len() > 1)
+            .map(|((nt, terminal), productions)| Conflict {
+                non_terminal: self.cfg.non_terminals.get(nt).unwrap(),
+                terminal: terminal.clone(),
+                productions,
+            })
+            .collect()
+    }
+
+    pub fn write(&self, out: &mut dyn Write, format: ReportFormat) -> io::Result<()> {
+        match format {
+            ReportFormat::Text => self.write_text(out),
+            ReportFormat::Json => self.write_json(out),
+            ReportFormat::Csv => self.write_csv(out),
+            ReportFormat::Markdown => self.write_markdown(out),
+        }
+    }
+
+    /// The table's columns, which are the same for every row.
+    fn columns(&self) -> Vec<&Terminal> {
+        self.table
+            .table
+            .values()
+            .next()
+            .map(|row| row.keys().collect())
+            .unwrap_or_default()
+    }
+
+    fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self
+            .cfg
+            .non_terminals
+            .iter()
+            .map(|nt| nt.non_terminal().len())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(out, "Productions:")?;
+        for (idx, production) in self.cfg.productions.iter().enumerate() {
+            let nt = lhs(self.cfg, idx);
+            writeln!(
+                out,
+                "[{:>2}] {: <w$} -> {}",
+                idx,
+                nt.non_terminal(),
+                production,
+                w = width
+            )?;
+        }
+
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Nullable: {}",
+            join(self.nullable.iter().map(|nt| nt.non_terminal()))
+        )?;
+
+        for (title, sets) in &[("First Sets:", &self.first), ("Follow Sets:", &self.follow)] {
+            writeln!(out)?;
+            writeln!(out, "{}", title)?;
+
+            for (nt, set) in sets.iter() {
+                writeln!(
+                    out,
+                    "{: <w$} : {{{}}}",
+                    nt.non_terminal(),
+                    terminals(set),
+                    w = width
+                )?;
+            }
+        }
+
+        writeln!(out)?;
+        writeln!(out, "Predict Sets:")?;
+        for (idx, set) in self.predict.iter().enumerate() {
+            let nt = lhs(self.cfg, idx);
+            writeln!(
+                out,
+                "[{:>2}] {: <w$} : {{{}}}",
+                idx,
+                nt.non_terminal(),
+                terminals(set),
+                w = width
+            )?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "Table:")?;
+
+        let columns = self.columns();
+        let cell = columns
+            .iter()
+            .map(|t| t.terminal().len())
+            .chain(Some(self.cfg.productions.len().to_string().len()))
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        write!(out, "{: <w$}", "", w = width + 2)?;
+        for terminal in &columns {
+            write!(out, "{: >c$}", terminal.terminal(), c = cell)?;
+        }
+        writeln!(out)?;
+
+        for (nt, row) in &self.table.table {
+            write!(out, "{: <w$}", nt.non_terminal(), w = width + 2)?;
+
+            for transition in row.values() {
+                match transition {
+                    Some(production) => write!(out, "{: >c$}", production, c = cell)?,
+                    None => write!(out, "{: >c$}", "-", c = cell)?,
+                }
+            }
+
+            writeln!(out)?;
+        }
+
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Conflicts:")?;
+
+            for conflict in &conflicts {
+                writeln!(
+                    out,
+                    "{: <w$} on {}: productions {}",
+                    conflict.non_terminal.non_terminal(),
+                    conflict.terminal.terminal(),
+                    join(conflict.productions.iter()),
+                    w = width
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        let list = |items: Vec<String>| format!("[{}]", items.join(", "));
+        let set = |set: &BTreeSet<Terminal>| list(set.iter().map(|t| json(t.terminal())).collect());
+        let map = |sets: &BTreeMap<&NonTerminal, BTreeSet<Terminal>>| {
+            let entries: Vec<String> = sets
+                .iter()
+                .map(|(nt, s)| format!("    {}: {}", json(nt.non_terminal()), set(s)))
+                .collect();
+            format!("{{\n{}\n  }}", entries.join(",\n"))
+        };
+
+        writeln!(out, "{{")?;
+        writeln!(
+            out,
+            "  \"start\": {},",
+            json(self.cfg.start_symbol.non_terminal())
+        )?;
+
+        let productions: Vec<String> = self
+            .cfg
+            .productions
+            .iter()
+            .enumerate()
+            .map(|(idx, production)| {
+                let rhs = production
+                    .symbols()
+                    .iter()
+                    .map(|s| json(s.as_str()))
+                    .collect();
+                format!(
+                    "    {{\"lhs\": {}, \"rhs\": {}}}",
+                    json(lhs(self.cfg, idx).non_terminal()),
+                    list(rhs)
+                )
+            })
+            .collect();
+        writeln!(
+            out,
+            "  \"productions\": [\n{}\n  ],",
+            productions.join(",\n")
+        )?;
+
+        let nullable = self
+            .nullable
+            .iter()
+            .map(|nt| json(nt.non_terminal()))
+            .collect();
+        writeln!(out, "  \"nullable\": {},", list(nullable))?;
+        writeln!(out, "  \"first\": {},", map(&self.first))?;
+        writeln!(out, "  \"follow\": {},", map(&self.follow))?;
+
+        let predict: Vec<String> = self
+            .predict
+            .iter()
+            .map(|s| format!("    {}", set(s)))
+            .collect();
+        writeln!(out, "  \"predict\": [\n{}\n  ],", predict.join(",\n"))?;
+
+        let rows: Vec<String> = self
+            .table
+            .table
+            .iter()
+            .map(|(nt, row)| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|(t, cell)| {
+                        let cell = cell.map_or_else(|| String::from("null"), |p| p.to_string());
+                        format!("{}: {}", json(t.terminal()), cell)
+                    })
+                    .collect();
+                format!("    {}: {{{}}}", json(nt.non_terminal()), cells.join(", "))
+            })
+            .collect();
+        writeln!(out, "  \"table\": {{\n{}\n  }},", rows.join(",\n"))?;
+
+        let conflicts: Vec<String> = self
+            .conflicts()
+            .iter()
+            .map(|c| {
+                format!(
+                    "    {{\"non_terminal\": {}, \"terminal\": {}, \"productions\": {}}}",
+                    json(c.non_terminal.non_terminal()),
+                    json(c.terminal.terminal()),
+                    list(c.productions.iter().map(|p| p.to_string()).collect())
+                )
+            })
+            .collect();
+
+        if conflicts.is_empty() {
+            writeln!(out, "  \"conflicts\": []")?;
+        } else {
+            writeln!(out, "  \"conflicts\": [\n{}\n  ]", conflicts.join(",\n"))?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "section,non_terminal,production,terminal")?;
+
+        let mut row =
+            |section: &str, nt: &NonTerminal, production: Option<usize>, terminal: &str| {
+                let production = production.map(|p| p.to_string()).unwrap_or_default();
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    section,
+                    csv(nt.non_terminal()),
+                    production,
+                    csv(terminal)
+                )
+            };
+
+        for nt in &self.nullable {
+            row("nullable", nt, None, "")?;
+        }
+
+        for (section, sets) in &[("first", &self.first), ("follow", &self.follow)] {
+            for (nt, set) in sets.iter() {
+                for terminal in set {
+                    row(section, nt, None, terminal.terminal())?;
+                }
+            }
+        }
+
+        for (idx, set) in self.predict.iter().enumerate() {
+            for terminal in set {
+                row(
+                    "predict",
+                    &lhs(self.cfg, idx),
+                    Some(idx),
+                    terminal.terminal(),
+                )?;
+            }
+        }
+
+        for (nt, cells) in &self.table.table {
+            for (terminal, cell) in cells {
+                if let Some(production) = cell {
+                    row("table", nt, Some(*production), terminal.terminal())?;
+                }
+            }
+        }
+
+        for conflict in self.conflicts() {
+            for production in &conflict.productions {
+                row(
+                    "conflict",
+                    conflict.non_terminal,
+                    Some(*production),
+                    conflict.terminal.terminal(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_markdown(&self, out: &mut dyn Write) -> io::Result<()> {
+        let code = |s: &str| format!("`{}`", s.replace('|', "\\|"));
+        let set = |set: &BTreeSet<Terminal>| {
+            set.iter()
+                .map(|t| code(t.terminal()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        writeln!(out, "## Productions\n")?;
+        writeln!(out, "| # | Production | Predict |")?;
+        writeln!(out, "|--:|------------|---------|")?;
+        for (idx, production) in self.cfg.productions.iter().enumerate() {
+            writeln!(
+                out,
+                "| {} | {} → {}| {} |",
+                idx,
+                lhs(self.cfg, idx).non_terminal(),
+                production,
+                set(&self.predict[idx])
+            )?;
+        }
+
+        writeln!(out, "\n## Sets\n")?;
+        writeln!(out, "| Non-terminal | Nullable | First | Follow |")?;
+        writeln!(out, "|--------------|----------|-------|--------|")?;
+        for nt in &self.cfg.non_terminals {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                nt.non_terminal(),
+                if self.nullable.contains(nt) {
+                    "yes"
+                } else {
+                    "no"
+                },
+                set(&self.first[nt]),
+                set(&self.follow[nt])
+            )?;
+        }
+
+        let columns = self.columns();
+        writeln!(out, "\n## LL(1) Table\n")?;
+        write!(out, "| |")?;
+        for terminal in &columns {
+            write!(out, " {} |", code(terminal.terminal()))?;
+        }
+        writeln!(out)?;
+        writeln!(out, "|---|{}", "--:|".repeat(columns.len()))?;
+
+        for (nt, row) in &self.table.table {
+            write!(out, "| {} |", nt.non_terminal())?;
+            for cell in row.values() {
+                match cell {
+                    Some(production) => write!(out, " {} |", production)?,
+                    None => write!(out, " |")?,
+                }
+            }
+            writeln!(out)?;
+        }
+
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            writeln!(out, "\n## Conflicts\n")?;
+
+            for conflict in &conflicts {
+                writeln!(
+                    out,
+                    "- {} on {}: productions {}",
+                    conflict.non_terminal.non_terminal(),
+                    code(conflict.terminal.terminal()),
+                    join(conflict.productions.iter())
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn lhs(cfg: &CFG, production_idx: usize) -> NonTerminal {
+    cfg.lhs(production_idx)
+        .expect("A production must have a left hand side.")
+        .clone()
+}
+
+fn join<T: ToString>(items: impl Iterator<Item = T>) -> String {
+    items.map(|i| i.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn terminals(set: &BTreeSet<Terminal>) -> String {
+    join(set.iter().map(|t| t.terminal()))
+}
+
+fn json(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+fn csv(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(cfg: &CFG, format: ReportFormat) -> String {
+        let mut out = Vec::new();
+        GrammarAnalysis::new(cfg).write(&mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn regex_grammar_sets() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+        let analysis = GrammarAnalysis::new(&cfg);
+        let names = |set: &BTreeSet<Terminal>| terminals(set);
+        let nt = NonTerminal::new;
+
+        assert_eq!(
+            join(analysis.nullable.iter().map(|nt| nt.non_terminal())),
+            "ALT, ALTLIST, ATOMMOD, CHARRNG, SEQ, SEQLIST"
+        );
+        assert_eq!(names(&analysis.first[&nt("ATOM")]), "char, dot, open");
+        assert_eq!(names(&analysis.follow[&nt("RE")]), "$");
+        assert_eq!(names(&analysis.follow[&nt("ALT")]), "$, close");
+        assert_eq!(names(&analysis.follow[&nt("SEQLIST")]), "$, close, pipe");
+        assert_eq!(
+            names(&analysis.follow[&nt("ATOMMOD")]),
+            "$, char, close, dot, open, pipe"
+        );
+        assert_eq!(names(&analysis.predict[7]), "$, close, pipe");
+        assert!(analysis.conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts() {
+        let cfg: CFG = "S -> a b | a c | lambda".parse().unwrap();
+        let analysis = GrammarAnalysis::new(&cfg);
+
+        assert_eq!(
+            analysis.conflicts(),
+            vec![Conflict {
+                non_terminal: &cfg.start_symbol,
+                terminal: Terminal::new("a"),
+                productions: vec![0, 1],
+            }]
+        );
+        assert!(
+            report(&cfg, ReportFormat::Text).ends_with("Conflicts:\nS on a: productions 0, 1\n")
+        );
+    }
+
+    #[test]
+    fn formats() {
+        let cfg: CFG = "S -> 'a,b' S | lambda".parse().unwrap();
+
+        assert_eq!(
+            report(&cfg, ReportFormat::Text),
+            "Productions:\n\
+             [ 0] S -> a,b S \n\
+             [ 1] S -> lambda \n\
+             \n\
+             Nullable: S\n\
+             \n\
+             First Sets:\n\
+             S : {a,b}\n\
+             \n\
+             Follow Sets:\n\
+             S : {$}\n\
+             \n\
+             Predict Sets:\n\
+             [ 0] S : {a,b}\n\
+             [ 1] S : {$}\n\
+             \n\
+             Table:\n\
+             \x20     $ a,b\n\
+             S     1   0\n"
+        );
+
+        assert_eq!(
+            report(&cfg, ReportFormat::Csv),
+            "section,non_terminal,production,terminal\n\
+             nullable,S,,\n\
+             first,S,,\"a,b\"\n\
+             follow,S,,$\n\
+             predict,S,0,\"a,b\"\n\
+             predict,S,1,$\n\
+             table,S,1,$\n\
+             table,S,0,\"a,b\"\n"
+        );
+
+        let json = report(&cfg, ReportFormat::Json);
+        assert!(json.contains("\"rhs\": [\"a,b\", \"S\"]"));
+        assert!(json.contains("\"table\": {\n    \"S\": {\"$\": 1, \"a,b\": 0}\n  },"));
+
+        let markdown = report(&cfg, ReportFormat::Markdown);
+        assert!(markdown.contains("| S | yes | `a,b` | `$` |\n"));
+        assert!(markdown.contains("| | `$` | `a,b` |\n|---|--:|--:|\n| S | 1 | 0 |\n"));
+    }
+}
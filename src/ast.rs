@@ -1,7 +1,7 @@
-use crate::symbol::NonTerminal;
+use crate::regex::Regex;
 use petgraph::dot::{Config, Dot};
 use petgraph::Graph;
-use silly_lex::Token;
+use std::convert::TryFrom;
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
@@ -31,6 +31,7 @@ impl fmt::Display for AstKind {
         match self {
             AstKind::Char(c) => write!(f, "{}", c),
             AstKind::Kleene => write!(f, "*"),
+            AstKind::Plus => write!(f, "+"),
             AstKind::Dot => write!(f, "."),
             AstKind::Lambda => write!(f, "λ"),
             _ => write!(f, "{:?}", self),
@@ -148,6 +149,11 @@ impl AstNode {
         }
     }
 
+    /// Prints a simplified AST back as a regex, see `Regex`'s `Display`.
+    pub fn to_regex(&self) -> std::result::Result<String, crate::error::Error> {
+        Regex::try_from(self).map(|regex| regex.to_string())
+    }
+
     // Export a graph to something that Graphvis can us
     pub fn export_graph(&self, file_path: impl AsRef<Path>) {
         let graph = self.create_pet_graph();
@@ -185,145 +191,12 @@ impl AstNode {
     }
 }
 
-pub fn simplify_RE(root_node: &AstNode) -> AstNode {
-    simplify_alt(&root_node.children[0])
-}
-
-pub fn simplify_plus(mut node: AstNode) -> AstNode {
-    let mut new_seq = AstNode::new(AstKind::Seq);
-    let mut kleene = AstNode::new(AstKind::Kleene);
-    let mut copy = node.clone();
-    kleene.children.append(&mut node.children);
-    new_seq.children.append(&mut copy.children);
-    new_seq.children.push(kleene);
-
-    new_seq
-}
-
-pub fn simplify_atom(atom_node: &AstNode) -> AstNode {
-    let mut nucleus = simplify_nucleus(&atom_node.children[0]);
-    let atom_mod = &atom_node.children[1].children[0];
-    let mut new_atom = AstNode::new(AstKind::Atom);
-    match atom_mod.kind {
-        AstKind::Kleene => {
-            let mut kleene = AstNode::new(AstKind::Kleene);
-            kleene.children.append(&mut nucleus.children);
-            new_atom.children.push(kleene);
-            new_atom
-        },
-        AstKind::Plus => {
-            new_atom.children.push(simplify_plus(nucleus));
-            new_atom
-        },
-        AstKind::Lambda => {
-            new_atom.children.append(&mut nucleus.children);
-            new_atom
-        },
-        _ => panic!("Bad Atom Mod"),
-    }
-}
-
-pub fn simplify_nucleus(nucleus_node: &AstNode) -> AstNode {
-    let mut new_nuc = AstNode::new(AstKind::Nucleus);
-    if nucleus_node.children.len() == 1 {
-        let dot = AstNode::new(AstKind::Dot);
-        new_nuc.children.push(dot);
-        return new_nuc
-    }
-    if nucleus_node.children[1].kind == AstKind::CharRng {
-        if nucleus_node.children[1].children.len() > 1 {
-            let mut alt = AstNode::new(AstKind::Alt);
-            let c = nucleus_node.children[0].kind.char().unwrap();
-            let m = nucleus_node.children[1].children[1].kind.char().unwrap();
-            let start: u8 = c as u8;
-            let end: u8 = m as u8;
-            let range = start..=end;
-
-            for i in range {
-                alt.children.push(AstNode::new(AstKind::Char(i as char)));
-            }
-            new_nuc.children.push(alt);
-        } else {
-            new_nuc.children.push(nucleus_node.children[0].clone());
-        }
-        return new_nuc;
-    } else {
-        // we're dealing with an alt!
-        let alt = simplify_alt(&nucleus_node.children[1]);
-        new_nuc.children.push(alt);
-        return new_nuc;
-    }
-}
-
-pub fn simplify_alt(alt_node: &AstNode) -> AstNode {
-    let mut new_alt = AstNode::new(AstKind::Alt);
-    let seq = simplify_seq(&alt_node.children[0]);
-    let mut alt = simplify_alt_list(&alt_node.children[1]);
-
-    if alt_node.children[1].children.len() == 1 {
-        return seq;
-    }
-
-    new_alt.children.push(seq);
-    new_alt.children.append(&mut alt.children);
-
-    new_alt
-}
-
-pub fn simplify_alt_list(altlist_node: &AstNode) -> AstNode {
-    let mut new_alt = AstNode::new(AstKind::Alt);
-    if (altlist_node.children.len() == 1) {
-        return new_alt;
-    }
-
-    let mut seq = simplify_seq(&altlist_node.children[1]);
-    let mut alt = simplify_alt_list(&altlist_node.children[2]);
-
-    new_alt.children.push(seq);
-    new_alt.children.append(&mut alt.children);
-
-    new_alt
-}
-
-pub fn simplify_seq(node: &AstNode) -> AstNode {
-    let mut new_seq = AstNode::new(AstKind::Seq);
-
-    if node.children[0].kind == AstKind::Lambda {
-        return AstNode::new(AstKind::Lambda);
-    }
-
-    let mut atom = simplify_atom(&node.children[0]);
-    let mut seqlist = simplify_seq_list(&node.children[1]);
-
-    new_seq.children.append(&mut atom.children);
-    new_seq.children.append(&mut seqlist.children);
-    
-    if new_seq.children.len() == 1 {
-        return new_seq.children.pop().unwrap();
-    }
-
-    new_seq
-}
-
-pub fn simplify_seq_list(node: &AstNode) -> AstNode {
-    let mut new_seq = AstNode::new(AstKind::Seq);
-
-    if node.children[0].kind == AstKind::Lambda {
-        return new_seq;
-    }
-
-    let mut atom = simplify_atom(&node.children[0]);
-    let mut seqlist = simplify_seq_list(&node.children[1]);
-
-    new_seq.children.append(&mut atom.children);
-    new_seq.children.append(&mut seqlist.children);
-
-    new_seq
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llre;
+    use crate::parser::Parser;
+    use crate::rewrite::Rewriter;
     use std::fs::create_dir;
     use std::process::Command;
 
@@ -437,7 +310,9 @@ mod tests {
             .output()
             .expect("failed to execute process");
 
-        let simple = simplify_RE(&y);
+        let parser = Parser::new(&llre::GRAMMAR, &llre::TABLE);
+        let mut lexer = silly_lex::Lexer::new("a-d.").iter().peekable();
+        let simple = Rewriter::regex().parse(&parser, &mut lexer).unwrap();
         simple.export_graph("test_output/AST.dot");
         Command::new("dot")
             .arg("-Tpng")
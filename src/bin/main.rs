@@ -1,68 +1,437 @@
 #![allow(non_snake_case)]
 
+use anyhow::Context;
+use wreck::alphabet_translator::alphabet_translator;
+use wreck::analysis::{GrammarAnalysis, ReportFormat};
 use wreck::cfg::CFG;
-use wreck::input::LexerConfig;
+use wreck::classes::{write_rows, CharClasses};
+use wreck::codegen::write_parser;
+use wreck::compress::CompressedTable;
+use wreck::dfa::Dfa;
+use wreck::differential::{to_pattern, verify_against};
+use wreck::equiv::distinguishing_string;
+use wreck::examples::{Examples, Rng};
+use wreck::input::{parse_alphabet, LexerConfig};
+use wreck::lint::lint;
 use wreck::ll_table::LLTable;
+use wreck::llre;
 use wreck::nfa_generator::NFAGenerator;
+use wreck::optimize::optimize;
+use wreck::overlap::conflicts;
 use wreck::parser::Parser;
+use wreck::reference_table::ReferenceTable;
+use wreck::regex::Regex;
+use wreck::rewrite::Rewriter;
+use wreck::utf8;
 
 use std::collections::BTreeSet;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
 #[derive(Debug, Clone, StructOpt)]
 pub struct Args {
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
     #[structopt(parse(from_os_str))]
-    output: PathBuf,
+    output: Option<PathBuf>,
+    /// Parses the regexes with this grammar instead of the built-in llre.cfg
+    #[structopt(long, parse(from_os_str))]
+    grammar: Option<PathBuf>,
+    #[structopt(flatten)]
+    tables: TableOptions,
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
-fn main() {
+#[derive(Debug, Clone, StructOpt)]
+struct TableOptions {
+    /// Compiles the tokens to automata over the 256 bytes of their UTF-8
+    /// encoding instead of over the characters of the alphabet
+    #[structopt(long)]
+    bytes: bool,
+    /// Also writes a DFA table for each token with a column per character
+    /// class instead of per character, and the classes next to the output
+    #[structopt(long)]
+    classes: bool,
+    /// Also writes a DFA table for each token to <token>.ctt, packed into the
+    /// default, base, next and check arrays of lex
+    #[structopt(long)]
+    compress: bool,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+enum Command {
+    /// Prints the nullable, FIRST, FOLLOW and PREDICT sets and the LL(1) table of a grammar
+    Grammar {
+        #[structopt(parse(from_os_str))]
+        grammar: PathBuf,
+        /// One of text, json, csv or markdown
+        #[structopt(long, default_value = "text")]
+        format: ReportFormat,
+        /// Instead of the report, compares the LL(1) table against a reference
+        /// table like src/re_table.txt and lists the cells that differ
+        #[structopt(long, parse(from_os_str))]
+        reference: Option<PathBuf>,
+    },
+    /// Prints a standalone Rust module with a recursive-descent parser for a grammar
+    Codegen {
+        #[structopt(parse(from_os_str))]
+        grammar: PathBuf,
+    },
+    /// Decides whether two regexes match the same strings, and prints the
+    /// shortest string only one of them matches when they do not
+    Equiv {
+        first: String,
+        second: String,
+        /// The alphabet in the syntax of the first line of a .lut file, by
+        /// default the characters of the two regexes. Required when either
+        /// regex contains `.`
+        #[structopt(long)]
+        alphabet: Option<String>,
+    },
+    /// Lists the pairs of tokens of a .lut file that match a common string,
+    /// and the tokens an earlier one completely shadows
+    Overlaps {
+        #[structopt(parse(from_os_str))]
+        lut: PathBuf,
+    },
+    /// Prints the first strings a regex matches in order of length, random
+    /// ones, and strings one edit away that it rejects
+    Examples {
+        regex: String,
+        /// The alphabet in the syntax of the first line of a .lut file, by
+        /// default the characters of the regex
+        #[structopt(long)]
+        alphabet: Option<String>,
+        /// How many strings of each kind to print
+        #[structopt(short, long, default_value = "10")]
+        count: usize,
+        /// The longest random string to generate
+        #[structopt(long, default_value = "16")]
+        max_length: usize,
+        #[structopt(long, default_value = "1")]
+        seed: u64,
+    },
+    /// Checks the automata of every token of a .lut file against the regex
+    /// crate on random strings, and prints the smallest one they disagree on
+    Verify {
+        #[structopt(parse(from_os_str))]
+        lut: PathBuf,
+        /// How many random strings to try per token
+        #[structopt(long, default_value = "1000")]
+        count: usize,
+        /// The longest random string to try
+        #[structopt(long, default_value = "12")]
+        max_length: usize,
+        #[structopt(long, default_value = "1")]
+        seed: u64,
+    },
+    /// Checks a .lut file for undeclared and unused characters, reversed
+    /// ranges, duplicate ids, nullable and shadowed tokens
+    Lint {
+        #[structopt(parse(from_os_str))]
+        lut: PathBuf,
+    },
+    /// Prints a .ctt table written with --compress as a dense .tt table
+    Decompress {
+        #[structopt(parse(from_os_str))]
+        table: PathBuf,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::from_args();
 
-    let config = LexerConfig::from_file(args.input);
+    match args.command {
+        Some(Command::Grammar {
+            grammar,
+            format,
+            reference: None,
+        }) => {
+            let cfg = CFG::from_file(grammar)?;
+            let stdout = std::io::stdout();
+            return Ok(GrammarAnalysis::new(&cfg).write(&mut stdout.lock(), format)?);
+        }
+        Some(Command::Grammar {
+            grammar,
+            reference: Some(reference),
+            ..
+        }) => return compare_reference(grammar, reference),
+        Some(Command::Codegen { grammar }) => {
+            let cfg = CFG::from_file(grammar)?;
+            let stdout = std::io::stdout();
+            return Ok(write_parser(
+                &cfg,
+                &LLTable::from_cfg(&cfg),
+                &mut stdout.lock(),
+            )?);
+        }
+        Some(Command::Equiv {
+            first,
+            second,
+            alphabet,
+        }) => return equiv(&first, &second, alphabet.as_deref()),
+        Some(Command::Overlaps { lut }) => {
+            for conflict in conflicts(&LexerConfig::from_file(lut))? {
+                println!("{}", conflict);
+            }
+
+            return Ok(());
+        }
+        Some(Command::Examples {
+            regex,
+            alphabet,
+            count,
+            max_length,
+            seed,
+        }) => {
+            let parsed = regex
+                .parse::<Regex>()
+                .with_context(|| format!("Could not parse the regex {:?}", regex))?;
+            let alphabet = match alphabet {
+                Some(alphabet) => parse_alphabet(&alphabet),
+                None => parsed.chars().chars().collect(),
+            };
+            let mut examples = Examples::new(&parsed, &alphabet);
+
+            let first = examples.first(count);
+            let random = examples.random(count, max_length, &mut Rng::new(seed));
+            let rejected = examples.near_misses(&first, count);
+
+            for (title, strings) in &[
+                ("First", &first),
+                ("Random", &random),
+                ("Rejected", &rejected),
+            ] {
+                println!("{}:", title);
+
+                for string in strings.iter() {
+                    println!("  {:?}", string);
+                }
+            }
+
+            return Ok(());
+        }
+        Some(Command::Verify {
+            lut,
+            count,
+            max_length,
+            seed,
+        }) => return verify_lut(lut, count, max_length, seed),
+        Some(Command::Lint { lut }) => {
+            let lints = lint(&LexerConfig::from_file(lut))?;
+
+            for lint in &lints {
+                println!("{}", lint);
+            }
+
+            if !lints.is_empty() {
+                anyhow::bail!("{} problems found", lints.len());
+            }
+
+            return Ok(());
+        }
+        Some(Command::Decompress { table }) => {
+            let table = CompressedTable::from_file(table)?;
+            let stdout = std::io::stdout();
+            return Ok(write_rows(
+                &mut stdout.lock(),
+                &table.decode(),
+                &table.accepting,
+            )?);
+        }
+        None => {}
+    }
+
+    let (input, output) = match (args.input, args.output) {
+        (Some(input), Some(output)) => (input, output),
+        _ => Error::with_description(
+            "Expected an <input> and <output> file or a subcommand",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
+    };
+
+    let config = LexerConfig::from_file(input);
 
     println!("Alphabet: {:?}\n", config.alphabet);
 
-    glue(&config, args.output);
+    match args.grammar {
+        Some(grammar) => {
+            let cfg = CFG::from_file(grammar)?;
+            glue(
+                &config,
+                &cfg,
+                &LLTable::from_cfg(&cfg),
+                output,
+                &args.tables,
+            )
+        }
+        None => glue(&config, &llre::GRAMMAR, &llre::TABLE, output, &args.tables),
+    }
+}
+
+fn compare_reference(grammar: PathBuf, reference: PathBuf) -> anyhow::Result<()> {
+    let cfg = CFG::from_file(grammar)?;
+    let table = LLTable::from_cfg(&cfg);
+    let mismatches = ReferenceTable::from_file(reference)?.diff(&cfg, &table);
+
+    for mismatch in &mismatches {
+        println!("{}", mismatch);
+    }
+
+    if !mismatches.is_empty() {
+        anyhow::bail!("{} cells differ from the reference", mismatches.len());
+    }
+
+    println!("The table matches the reference");
+    Ok(())
+}
+
+fn equiv(first: &str, second: &str, alphabet: Option<&str>) -> anyhow::Result<()> {
+    let parse = |regex: &str| {
+        regex
+            .parse::<Regex>()
+            .with_context(|| format!("Could not parse the regex {:?}", regex))
+    };
+    let (first_regex, second_regex) = (parse(first)?, parse(second)?);
+
+    let alphabet: BTreeSet<char> = match alphabet {
+        Some(alphabet) => parse_alphabet(alphabet),
+        // `.` would stand for nothing but the characters the regexes name.
+        None if first_regex.contains_any() || second_regex.contains_any() => {
+            anyhow::bail!("--alphabet is required when a regex contains `.`")
+        }
+        None => first_regex
+            .chars()
+            .union(&second_regex.chars())
+            .chars()
+            .collect(),
+    };
+
+    match distinguishing_string(&first_regex, &second_regex, &alphabet) {
+        None => println!("{} and {} are equivalent", first, second),
+        Some(witness) => {
+            let matched = match Dfa::from_regex(&first_regex, &alphabet).accepts(&witness) {
+                true => first,
+                false => second,
+            };
+
+            println!(
+                "{:?} is matched by {} but not by the other",
+                witness, matched
+            );
+            anyhow::bail!("{} and {} are not equivalent", first, second);
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_lut(lut: PathBuf, count: usize, max_length: usize, seed: u64) -> anyhow::Result<()> {
+    let config = LexerConfig::from_file(lut);
+    let mut rng = Rng::new(seed);
+    let mut failed = 0;
+
+    for (regex, id, _) in &config.regexes {
+        let regex = regex
+            .parse::<Regex>()
+            .with_context(|| format!("Could not parse the regex for {}", id))?;
+        // The pattern comes from the regex as written, so a bad optimization
+        // shows up as well.
+        let pattern = to_pattern(&regex, &config.alphabet);
+        let optimized = optimize(regex);
+
+        match verify_against(
+            &optimized,
+            &pattern,
+            &config.alphabet,
+            count,
+            max_length,
+            &mut rng,
+        )? {
+            None => println!("{}: agrees with {}", id, pattern),
+            Some(disagreement) => {
+                println!("{}: {}", id, disagreement);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} tokens disagree with the regex crate", failed);
+    }
+
+    Ok(())
 }
 
 // TODO this should probably be moved to main, just doing it here so we don't get merge conflicts
-fn glue(config: &LexerConfig, output: impl AsRef<std::path::Path>) {
+fn glue(
+    config: &LexerConfig,
+    cfg: &CFG,
+    table: &LLTable,
+    output: impl AsRef<std::path::Path>,
+    options: &TableOptions,
+) -> anyhow::Result<()> {
+    let alphabet = match options.bytes {
+        true => utf8::byte_alphabet(),
+        false => config.alphabet.clone(),
+    };
+
+    let classes_output = output.as_ref().with_extension("classes");
+    // Compressed tables get their own extension so nothing reads them as dense.
+    let table_extension = match options.compress {
+        true => "ctt",
+        false => "tt",
+    };
+    let mut dfas: Vec<Dfa> = Vec::new();
     let mut output = File::create(output).unwrap();
-    write_alphabet(&mut output, &config.alphabet);
+    write_alphabet(&mut output, &alphabet);
 
-    let cfg = CFG::from_file("llre.cfg").unwrap(); // TODO this is the only input, right?
-    let table = LLTable::from_cfg(&cfg);
     for input_line in &config.regexes {
         println!("working on {}", input_line.1);
-        let mut lexer = silly_lex::Lexer::new(&input_line.0).iter();
-        let mut parser = Parser::new(&cfg, &table);
-        let tree = parser.parse(&mut lexer.peekable());
-
-        let mut dot_output = input_line.1.clone();
-        dot_output.push_str(".dot");
-        tree.export_graph(&dot_output);
-
-        let simplified = wreck::ast::simplify_RE(&tree);
+        let parser = Parser::new(cfg, table);
+        let mut lexer = silly_lex::Lexer::new(&input_line.0).iter().peekable();
+        let simplified = Rewriter::regex()
+            .parse(&parser, &mut lexer)
+            .with_context(|| format!("Could not parse the regex for {}", input_line.1))?;
 
         let mut simplified_dot_output = input_line.1.clone();
         simplified_dot_output.push_str("_simple.dot");
 
         simplified.export_graph(&simplified_dot_output);
 
-        let mut generator = NFAGenerator::new(config.alphabet.clone(), Some(input_line.1.clone()));
+        let regex = Regex::try_from(&simplified)
+            .with_context(|| format!("Could not build the regex for {}", input_line.1))?;
+
+        config.check_nullable(&input_line.1, &regex)?;
+
+        let mut regex = optimize(regex);
+
+        if options.bytes {
+            regex = optimize(utf8::to_bytes(&regex, &config.alphabet));
+        }
+
+        let mut generator = NFAGenerator::new(alphabet.clone(), Some(input_line.1.clone()));
         // TODO these could probably be mixed together into a single 'generate' command
-        generator.add_to_table(&simplified, 0, 1);
+        generator.add_to_table(&regex, 0, 1);
+
+        // Writing the NFA consumes its lambda transitions.
+        if options.classes || options.compress {
+            dfas.push(Dfa::from_nfa(&generator, 0, 1));
+        }
+
         generator.create_nfa().unwrap();
 
         writeln!(
             output,
-            "{}.tt\t{}\t{}",
+            "{}.{}\t{}\t{}",
             input_line.1,
+            table_extension,
             input_line.1,
             input_line.2.as_deref().unwrap_or("")
         )
@@ -71,149 +440,44 @@ fn glue(config: &LexerConfig, output: impl AsRef<std::path::Path>) {
 
     output.flush().unwrap();
 
-    // let simplified = wreck::ast::simplify_RE(&tree);
-}
-
-fn write_alphabet(out: &mut dyn Write, alpha: &BTreeSet<char>) {
-    for c in alpha {
-        write!(out, "x{:02X}", *c as u8).unwrap();
-    }
-
-    writeln!(out).unwrap();
-}
-
-fn print_table(cfg: &CFG, lltable: &LLTable) {
-    println!("Productions:");
-
-    for (i, production) in cfg.productions.iter().enumerate() {
-        let nt = cfg
-            .production_map
-            .iter()
-            .find(|(_, v)| v.contains(&i))
-            .map(|(k, _)| k)
-            .expect("A production must have a left hand side.");
-
-        println!("[{:>2}] {: <8} -> {}", i, nt.non_terminal(), production);
+    if !options.classes && !options.compress {
+        return Ok(());
     }
 
-    println!();
-
-    println!("Table:");
-
-    print!("{: <10}", "");
-    let (_, row) = lltable.table.iter().next().unwrap();
+    let classes = match options.classes {
+        true => CharClasses::new(&alphabet, &dfas),
+        false => CharClasses::singletons(&alphabet),
+    };
 
-    for (terminal, _) in row.iter() {
-        print!("{: >8}", terminal.terminal());
+    if options.classes {
+        println!("{} characters in {} classes", alphabet.len(), classes.len());
+        classes.write(&mut File::create(classes_output)?)?;
     }
 
-    println!();
-
-    for (nt, row) in lltable.table.iter() {
-        print!("{: <10}", nt.non_terminal());
-
-        for (_, transition) in row.iter() {
-            if let Some(production) = transition {
-                print!("{: >8}", production)
-            } else {
-                print!("{: >8}", "-");
-            }
+    for ((_, name, _), dfa) in config.regexes.iter().zip(&dfas) {
+        let mut table = File::create(format!("{}.{}", name, table_extension))?;
+
+        if options.compress {
+            let compressed = CompressedTable::from_dfa(dfa, &classes);
+            println!(
+                "{}: {} of {} entries",
+                name,
+                compressed.next.len(),
+                dfa.states.len() * classes.len()
+            );
+            write!(table, "{}", compressed)?;
+        } else {
+            dfa.write_table(&mut table, &classes)?;
         }
-
-        println!();
     }
-    // for nt in &cfg.non_terminals {
 
-    //     for terminal in &cfg.terminals {
-    //         if let Some(production) = lltable.table[nt][terminal] {
-    //             print!("{: >8}", production)
-    //         } else {
-    //             print!("{: >8}", ".");
-    //         }
-    //     }
+    Ok(())
+}
 
-    //     println!();
-    // }
+fn write_alphabet(out: &mut dyn Write, alpha: &BTreeSet<char>) {
+    for c in alpha {
+        write!(out, "{}", alphabet_translator::char_to_hex(*c)).unwrap();
+    }
 
-    println!();
+    writeln!(out).unwrap();
 }
-
-// fn main() -> anyhow::Result<()> {
-//     let cfg = CFG::from_file("llre.cfg").unwrap();
-
-//     // println!("{:#?}", cfg);
-
-//     // // for nt in &cfg.non_terminals {
-//     // //     println!(
-//     // //         "{:?}: {:?}",
-//     // //         nt,
-//     // //         cfg.derives_to_lambda(&nt, &mut Vec::new())
-//     // //     );
-//     // // }
-
-//     println!("First Sets:");
-//     for nt in &cfg.non_terminals {
-//         println!("{: <8}: {:?}", nt.non_terminal(), cfg.first_set(&nt));
-//     }
-
-//     println!();
-
-//     println!("Follow Sets:");
-//     for nt in &cfg.non_terminals {
-//         println!(
-//             "{: <8}: {:?}",
-//             nt.non_terminal(),
-//             cfg.follow(&nt, Default::default()).0
-//         );
-//     }
-
-//     println!();
-
-//     println!("Predict Sets:");
-//     for (i, production) in cfg.productions.iter().enumerate() {
-//         let nt = cfg
-//             .production_map
-//             .iter()
-//             .find(|(_, v)| v.contains(&i))
-//             .map(|(k, _)| k)
-//             .expect("A production must have a left hand side.");
-
-//         println!(
-//             "[{:>2}] {: <8} -> {:?}",
-//             i,
-//             nt.non_terminal(),
-//             cfg.predict_set(nt, production)
-//         );
-//     }
-//     // for nt in &cfg.non_terminals {
-//     //     for production_index in &cfg.production_map[nt] {
-//     //         let production = &cfg.productions[*production_index];
-//     //         let predict_set = cfg.predict_set(nt, production);
-
-//     //         println!("{: <8}: {:?}", nt.non_terminal(), predict_set);
-//     //     }
-//     // }
-
-//     println!();
-
-//     let table = LLTable::from_cfg(&cfg);
-//     print_table(&cfg, &table);
-
-//     println!();
-//     println!();
-//     println!();
-
-//     let regex = "Ab(cd-e+)*(.|012)3";
-//     println!("Parsing Regex: {}", regex);
-
-//     let mut lexer = silly_lex::Lexer::new(&regex).iter();
-
-//     let mut parser = Parser::new(&cfg, &table);
-//     let tree = parser.parse(&mut lexer.peekable());
-
-//     tree.export_graph("regex.dot");
-//     let simplified = wreck::ast::simplify_RE(&tree);
-//     simplified.export_graph("regex_simpl.dot");
-
-//     Ok(())
-// }
@@ -1,10 +1,11 @@
+use crate::cfg_parser;
 use crate::production::Production;
 use crate::symbol::{NonTerminal, Symbol, Terminal};
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use std::collections::{BTreeMap, BTreeSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs::read_to_string;
 use std::path::Path;
+use std::str::FromStr;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CFG {
@@ -13,6 +14,9 @@ pub struct CFG {
     pub start_symbol: NonTerminal,
     pub production_map: BTreeMap<NonTerminal, Vec<usize>>,
     pub productions: Vec<Production>,
+    /// Action labels of productions, written `=> label` in grammar files.
+    /// See `Parser::parse_with`.
+    pub actions: BTreeMap<usize, String>,
 }
 
 impl CFG {
@@ -20,6 +24,80 @@ impl CFG {
         Self::default()
     }
 
+    /// Appends `nt -> symbols` and keeps the symbol sets and `production_map`
+    /// in sync. Returns the index of the new production.
+    pub fn add_production(&mut self, nt: NonTerminal, symbols: Vec<Symbol>) -> usize {
+        for symbol in &symbols {
+            match symbol {
+                Symbol::Terminal(t) => {
+                    self.terminals.insert(t.clone());
+                }
+                Symbol::NonTerminal(n) => {
+                    self.non_terminals.insert(n.clone());
+                }
+                Symbol::Lambda => {}
+            }
+        }
+
+        self.productions.push(symbols.into());
+        let production_idx = self.productions.len() - 1;
+
+        self.production_map
+            .entry(nt.clone())
+            .or_default()
+            .push(production_idx);
+        self.non_terminals.insert(nt);
+
+        production_idx
+    }
+
+    /// Returns a non-terminal named `{base}_{n}` that is not yet used in the grammar.
+    pub fn fresh_non_terminal(&self, base: &str) -> NonTerminal {
+        (1..)
+            .map(|n| NonTerminal::new(format!("{}_{}", base, n)))
+            .find(|nt| !self.non_terminals.contains(nt))
+            .unwrap()
+    }
+
+    /// The left hand side of the production at `production_idx`.
+    pub fn lhs(&self, production_idx: usize) -> Option<&NonTerminal> {
+        self.production_map
+            .iter()
+            .find(|(_, indices)| indices.contains(&production_idx))
+            .map(|(nt, _)| nt)
+    }
+
+    /// The set of non-terminals that can derive lambda, computed as a fixed point.
+    pub fn nullable(&self) -> BTreeSet<NonTerminal> {
+        let mut nullable = BTreeSet::new();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (nt, production_indices) in &self.production_map {
+                if nullable.contains(nt) {
+                    continue;
+                }
+
+                let derives_lambda = production_indices.iter().any(|idx| {
+                    self.productions[*idx].symbols().iter().all(|s| match s {
+                        Symbol::Lambda => true,
+                        Symbol::NonTerminal(n) => nullable.contains(n),
+                        Symbol::Terminal(_) => false,
+                    })
+                });
+
+                if derives_lambda {
+                    nullable.insert(nt.clone());
+                    changed = true;
+                }
+            }
+        }
+
+        nullable
+    }
+
     pub fn derives_to_lambda(
         &self,
         nt: &NonTerminal,
@@ -139,30 +217,36 @@ impl CFG {
 
         let mut f: BTreeSet<Terminal> = BTreeSet::new();
 
-        for (index, production) in self.productions.iter().enumerate() {
-            // If this production contains the symbol:
-            if let Some(index) = production.index_of(&symbol) {
-                let rest = if index + 1 == production.symbols().len() {
-                    &[]
-                } else {
-                    &production.symbols()[index + 1..]
-                };
+        // The end of the input follows the start symbol. Grammars that end
+        // their start rule with `$`, like llre.cfg, already get it from there,
+        // but without this a nullable start symbol has no table entry for `$`
+        // and the empty input is rejected:
+        if *nt == self.start_symbol {
+            f.insert(Terminal::new("$"));
+        }
+
+        for (production_idx, production) in self.productions.iter().enumerate() {
+            let symbols = production.symbols();
+
+            // Every occurrence of the symbol is followed by the first set of
+            // what comes after it, and by the follow set of the left hand side
+            // if all of that can derive lambda:
+            for (index, _) in symbols.iter().enumerate().filter(|(_, s)| **s == symbol) {
+                let rest = &symbols[index + 1..];
 
                 if !rest.is_empty() {
                     let (g, _i) = self.first(rest, BTreeSet::new());
                     f.extend(g.into_iter());
                 }
 
-                if !rest.is_empty()
-                    || (!self.contains_terminal(rest)
-                        && rest.iter().all(|s| {
-                            self.derives_to_lambda(s.non_terminal().unwrap(), &mut Vec::new())
-                        }))
+                if !self.contains_terminal(rest)
+                    && rest.iter().all(|s| {
+                        s.is_lambda()
+                            || self.derives_to_lambda(s.non_terminal().unwrap(), &mut Vec::new())
+                    })
                 {
                     let lhs_of_production = self
-                        .production_map
-                        .iter()
-                        .find_map(|(k, v)| if v.contains(&index) { Some(k) } else { None })
+                        .lhs(production_idx)
                         .expect("There must be a NonTerminal for a given production");
 
                     let (g, _s) = self.follow(lhs_of_production, t.clone());
@@ -200,131 +284,20 @@ impl CFG {
     }
 
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
-        let mut cfg = CFG::new();
-
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines().flatten().filter(|l| !l.is_empty());
-
-        // Get the start symbol and its first production
-        let (start, first_production) = lines
-            .next()
-            .ok_or(anyhow!("Cannot create an empty CFG"))
-            .map(Line::from_str)??
-            .into_start()?;
-
-        // Add the first production to the list of productions
-        let first_production: Production = first_production.into();
-        cfg.productions.push(first_production);
-        let production_idx = cfg.productions.len() - 1;
-
-        // Add the first production to the start symbol
-        cfg.production_map
-            .entry(start.clone())
-            .or_default()
-            .push(production_idx);
+        let path = path.as_ref();
+        let input = read_to_string(path)
+            .with_context(|| format!("Could not read grammar {}", path.display()))?;
 
-        cfg.non_terminals.insert(start.clone());
-
-        // Set the CFG start symbol
-        cfg.start_symbol = start.clone();
-
-        let mut current_nt = start;
-        while let Some(Ok(line)) = lines.next().map(Line::from_str) {
-            match line {
-                Line::Start(new_nt, new_productions) => {
-                    for symbol in &new_productions {
-                        if symbol.terminal().is_ok() {
-                            cfg.terminals.insert(symbol.terminal().unwrap().clone());
-                        }
-                    }
-
-                    let production: Production = new_productions.into();
-                    cfg.productions.push(production);
-                    let production_idx = cfg.productions.len() - 1;
-
-                    cfg.production_map
-                        .entry(new_nt.clone())
-                        .or_default()
-                        .push(production_idx);
-
-                    cfg.non_terminals.insert(new_nt.clone());
-
-                    current_nt = new_nt;
-                }
-                Line::Union(new_productions) => {
-                    for symbol in &new_productions {
-                        if symbol.terminal().is_ok() {
-                            cfg.terminals.insert(symbol.terminal().unwrap().clone());
-                        }
-                    }
-
-                    let production: Production = new_productions.into();
-                    cfg.productions.push(production);
-                    let production_idx = cfg.productions.len() - 1;
-
-                    cfg.production_map
-                        .entry(current_nt.clone())
-                        .or_default()
-                        .push(production_idx);
-                }
-            }
-        }
-
-        Ok(cfg)
+        input
+            .parse()
+            .with_context(|| format!("Could not parse grammar {}", path.display()))
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Line {
-    Start(NonTerminal, Vec<Symbol>),
-    Union(Vec<Symbol>),
-}
-
-impl Line {
-    fn into_start(self) -> Result<(NonTerminal, Vec<Symbol>)> {
-        if let Line::Start(nt, symbols) = self {
-            Ok((nt, symbols))
-        } else {
-            Err(anyhow!("Line is not a Start: {:?}", self))
-        }
-    }
-
-    fn into_union(self) -> Result<Vec<Symbol>> {
-        if let Line::Union(symbols) = self {
-            Ok(symbols)
-        } else {
-            Err(anyhow!("Line is not a Union: {:?}", self))
-        }
-    }
-
-    pub fn from_str(input: String) -> Result<Line> {
-        // println!("From Str: {:?}", input);
+impl FromStr for CFG {
+    type Err = anyhow::Error;
 
-        let mut split: Vec<&str> = input.trim().split(' ').collect();
-
-        match &split.as_slice() {
-            [nt, "->", symbols @ ..] => {
-                let nt = Symbol::from_parse(nt)?.non_terminal()?.clone();
-                let symbols = symbols
-                    .iter()
-                    .copied()
-                    .map(Symbol::from_parse)
-                    .collect::<Result<Vec<Symbol>>>()?;
-
-                Ok(Line::Start(nt, symbols))
-            }
-            ["|", symbols @ ..] => {
-                let symbols = symbols
-                    .iter()
-                    .copied()
-                    .map(Symbol::from_parse)
-                    .collect::<Result<Vec<Symbol>>>()?;
-
-                Ok(Line::Union(symbols))
-            }
-
-            _ => panic!(format!("Split: {:?}", split)),
-        }
+    fn from_str(input: &str) -> Result<Self> {
+        cfg_parser::parse(input)
     }
 }
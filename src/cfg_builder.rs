@@ -0,0 +1,216 @@
+use crate::cfg::CFG;
+use crate::cfg_parser::bare_symbol;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use anyhow::{anyhow, bail, Result};
+
+/// Builds a `CFG` in code, checking symbols and keeping the production
+/// indices consistent:
+///
+/// ```
+/// # use wreck::cfg_builder::CfgBuilder;
+/// let cfg = CfgBuilder::new()
+///     .rule("LIST", ["item", "TAIL"])
+///     .rule("TAIL", ["','", "item", "TAIL"])
+///     .rule("TAIL", ["lambda"])
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(cfg.productions.len(), 3);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CfgBuilder {
+    start: Option<String>,
+    rules: Vec<(String, Vec<String>, Option<String>)>,
+    /// An action given before any rule, reported by `build`.
+    orphan_action: Option<String>,
+}
+
+impl CfgBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the start symbol, which defaults to the left hand side of the first rule.
+    pub fn start(mut self, nt: impl Into<String>) -> Self {
+        self.start = Some(nt.into());
+        self
+    }
+
+    /// Adds the production `lhs -> rhs`. Symbols are spelled as in grammar
+    /// files: `lambda`, `$`, a terminal, a non-terminal, or a quoted terminal
+    /// like `'('`. An empty `rhs` is a lambda production.
+    pub fn rule<S: AsRef<str>>(
+        mut self,
+        lhs: impl Into<String>,
+        rhs: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let rhs = rhs.into_iter().map(|s| s.as_ref().to_string()).collect();
+        self.rules.push((lhs.into(), rhs, None));
+        self
+    }
+
+    /// Labels the most recently added rule with an action, like `=> label`
+    /// in a grammar file. `build` fails if no rule comes before it.
+    pub fn action(mut self, label: impl Into<String>) -> Self {
+        match self.rules.last_mut() {
+            Some(rule) => rule.2 = Some(label.into()),
+            None => self.orphan_action = Some(label.into()),
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<CFG> {
+        if let Some(label) = self.orphan_action {
+            bail!("The action {:?} comes before any rule", label);
+        }
+
+        let mut cfg = CFG::new();
+
+        for (lhs, rhs, action) in &self.rules {
+            let lhs = match parse_symbol(lhs)? {
+                Symbol::NonTerminal(nt) => nt,
+                _ => bail!(
+                    "The left hand side of a rule must be a non-terminal, found {:?}",
+                    lhs
+                ),
+            };
+
+            let mut symbols = rhs
+                .iter()
+                .map(|s| parse_symbol(s))
+                .collect::<Result<Vec<Symbol>>>()?;
+
+            symbols.retain(|s| !s.is_lambda());
+            if symbols.is_empty() {
+                symbols.push(Symbol::Lambda);
+            }
+
+            let idx = cfg.add_production(lhs, symbols);
+
+            if let Some(label) = action {
+                cfg.actions.insert(idx, label.clone());
+            }
+        }
+
+        let first = self.rules.first().map(|(lhs, _, _)| lhs.clone());
+
+        let start = match self.start.or(first) {
+            Some(start) => NonTerminal::new(start),
+            None => bail!("Cannot create an empty CFG"),
+        };
+
+        if let Some(nt) = cfg
+            .non_terminals
+            .iter()
+            .chain(Some(&start))
+            .find(|nt| !cfg.production_map.contains_key(*nt))
+        {
+            bail!("Non-terminal {} has no productions", nt.non_terminal());
+        }
+
+        cfg.start_symbol = start;
+        Ok(cfg)
+    }
+}
+
+fn parse_symbol(s: &str) -> Result<Symbol> {
+    let quoted = s.len() >= 3
+        && ((s.starts_with('\'') && s.ends_with('\'')) || (s.starts_with('"') && s.ends_with('"')));
+
+    if quoted {
+        Ok(Symbol::from_terminal(Terminal::new(&s[1..s.len() - 1])))
+    } else {
+        bare_symbol(s).ok_or_else(|| {
+            anyhow!(
+                "Invalid symbol {:?}, quote terminals that contain punctuation",
+                s
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_grammar() {
+        let cfg = CfgBuilder::new()
+            .rule("RE", ["ALT", "$"])
+            .rule("ALT", ["SEQ", "ALTLIST"])
+            .action("alt")
+            .rule("ALTLIST", ["pipe", "SEQ", "ALTLIST"])
+            .action("alt_list")
+            .rule("ALTLIST", ["lambda"])
+            .action("lambda")
+            .rule("SEQ", ["ATOM", "SEQLIST"])
+            .action("seq")
+            .rule("SEQ", ["lambda"])
+            .action("seq")
+            .rule("SEQLIST", ["ATOM", "SEQLIST"])
+            .action("seq_list")
+            .rule("SEQLIST", ["lambda"])
+            .action("lambda")
+            .rule("ATOM", ["NUCLEUS", "ATOMMOD"])
+            .action("atom")
+            .rule("ATOMMOD", ["kleene"])
+            .rule("ATOMMOD", ["plus"])
+            .rule("ATOMMOD", Vec::<&str>::new())
+            .action("lambda")
+            .rule("NUCLEUS", ["open", "ALT", "close"])
+            .action("group")
+            .rule("NUCLEUS", ["char", "CHARRNG"])
+            .action("range")
+            .rule("NUCLEUS", ["dot"])
+            .rule("CHARRNG", ["dash", "char"])
+            .action("range_end")
+            .rule("CHARRNG", ["lambda"])
+            .action("lambda")
+            .build()
+            .unwrap();
+
+        assert_eq!(cfg, CFG::from_file("llre.cfg").unwrap());
+    }
+
+    #[test]
+    fn quoted_terminals_and_start() {
+        let cfg = CfgBuilder::new()
+            .start("E")
+            .rule("T", ["'('", "E", "\")\""])
+            .rule("T", ["num"])
+            .rule("E", ["T", "'+'", "T"])
+            .build()
+            .unwrap();
+
+        let terminals: Vec<&str> = cfg.terminals.iter().map(|t| t.terminal()).collect();
+
+        assert_eq!(cfg.start_symbol, NonTerminal::new("E"));
+        assert_eq!(terminals, vec!["(", ")", "+", "num"]);
+        assert_eq!(cfg.production_map[&NonTerminal::new("T")], vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_grammars() {
+        let error = |builder: CfgBuilder| builder.build().unwrap_err().to_string();
+
+        assert_eq!(error(CfgBuilder::new()), "Cannot create an empty CFG");
+        assert_eq!(
+            error(CfgBuilder::new().rule("A", ["B"])),
+            "Non-terminal B has no productions"
+        );
+        assert_eq!(
+            error(CfgBuilder::new().start("S").rule("A", ["a"])),
+            "Non-terminal S has no productions"
+        );
+        assert_eq!(
+            error(CfgBuilder::new().rule("a", ["b"])),
+            "The left hand side of a rule must be a non-terminal, found \"a\""
+        );
+        assert!(CfgBuilder::new().rule("A", ["("]).build().is_err());
+        assert!(CfgBuilder::new().rule("A", ["b c"]).build().is_err());
+        assert_eq!(
+            error(CfgBuilder::new().action("x").rule("A", ["a"])),
+            "The action \"x\" comes before any rule"
+        );
+    }
+}
@@ -0,0 +1,593 @@
+use crate::cfg::CFG;
+use crate::ebnf::Expr;
+use crate::error::Error;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A 1-based line and column in a grammar file.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    fn error(self, message: impl Into<String>) -> anyhow::Error {
+        Error::GrammarParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+        .into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Arrow,
+    /// `=>`, followed by the action label of an alternative.
+    Action,
+    Pipe,
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Star,
+    Plus,
+    Question,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    position: Position,
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            position: Position { line: 1, column: 1 },
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn tokens(mut self) -> Result<(Vec<Token>, Position)> {
+        let mut tokens = Vec::new();
+
+        while let Some(&c) = self.chars.peek() {
+            let position = self.position;
+
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                }
+                '#' => {
+                    while self.chars.peek().is_some_and(|c| *c != '\n') {
+                        self.bump();
+                    }
+                    continue;
+                }
+                '\'' | '"' => self.quoted(c)?,
+                c if is_word_char(c) => {
+                    let mut word = String::new();
+                    while let Some(c) = self.chars.peek().copied().filter(|c| is_word_char(*c)) {
+                        word.push(c);
+                        self.bump();
+                    }
+                    TokenKind::Word(word)
+                }
+                _ => {
+                    self.bump();
+
+                    match c {
+                        '-' if self.chars.peek() == Some(&'>') => {
+                            self.bump();
+                            TokenKind::Arrow
+                        }
+                        '=' if self.chars.peek() == Some(&'>') => {
+                            self.bump();
+                            TokenKind::Action
+                        }
+                        '|' => TokenKind::Pipe,
+                        '(' => TokenKind::Open,
+                        ')' => TokenKind::Close,
+                        '[' => TokenKind::OpenBracket,
+                        ']' => TokenKind::CloseBracket,
+                        '*' => TokenKind::Star,
+                        '+' => TokenKind::Plus,
+                        '?' => TokenKind::Question,
+                        c => {
+                            return Err(position.error(format!(
+                            "Unexpected character {:?}, quote terminals that contain punctuation",
+                            c
+                        )))
+                        }
+                    }
+                }
+            };
+
+            tokens.push(Token { kind, position });
+        }
+
+        Ok((tokens, self.position))
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<TokenKind> {
+        let start = self.position;
+        self.bump();
+
+        let mut terminal = String::new();
+
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => break,
+                Some('\\') => match self.bump() {
+                    Some(c) if c == quote || c == '\\' => terminal.push(c),
+                    _ => {
+                        return Err(start.error(format!(
+                            "Only \\{} and \\\\ can be escaped in a quoted terminal",
+                            quote
+                        )))
+                    }
+                },
+                Some('\n') | None => return Err(start.error("Unterminated quoted terminal")),
+                Some(c) => terminal.push(c),
+            }
+        }
+
+        if terminal.is_empty() {
+            return Err(start.error("Quoted terminals cannot be empty, use lambda"));
+        }
+
+        Ok(TokenKind::Quoted(terminal))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// A rule's left hand side with each alternative and its action label.
+type Rule = (NonTerminal, Vec<Vec<Expr>>, Vec<Option<String>>);
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    end: Position,
+    references: Vec<(NonTerminal, Position)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn peek_kind(&self) -> Option<&TokenKind> {
+        self.peek().map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn position(&self) -> Position {
+        self.peek().map_or(self.end, |t| t.position)
+    }
+
+    /// Whether the next two tokens are `NT ->`, the start of a new rule.
+    fn at_rule_start(&self) -> bool {
+        matches!(self.peek_kind(), Some(TokenKind::Word(_)))
+            && matches!(
+                self.tokens.get(self.index + 1).map(|t| &t.kind),
+                Some(TokenKind::Arrow)
+            )
+    }
+
+    fn rule(&mut self) -> Result<Rule> {
+        let position = self.position();
+
+        let lhs = match self.next().map(|t| t.kind) {
+            Some(TokenKind::Word(word)) => match symbol(&word, position)? {
+                Symbol::NonTerminal(nt) => nt,
+                _ => {
+                    return Err(position.error(format!(
+                        "The left hand side of a rule must be a non-terminal, found {:?}",
+                        word
+                    )))
+                }
+            },
+            _ => return Err(position.error("Expected a rule of the form `NT -> ...`")),
+        };
+
+        self.expect(TokenKind::Arrow, "`->`")?;
+
+        let mut alternatives = vec![self.sequence()?];
+        let mut actions = vec![self.action()?];
+
+        while self.peek_kind() == Some(&TokenKind::Pipe) {
+            self.next();
+            alternatives.push(self.sequence()?);
+            actions.push(self.action()?);
+        }
+
+        if self.peek().is_some() && !self.at_rule_start() {
+            let token = self.next().unwrap();
+            return Err(token
+                .position
+                .error(format!("Unexpected {}", describe(&token.kind))));
+        }
+
+        Ok((lhs, alternatives, actions))
+    }
+
+    /// An optional `=> label` at the end of an alternative.
+    fn action(&mut self) -> Result<Option<String>> {
+        if self.peek_kind() != Some(&TokenKind::Action) {
+            return Ok(None);
+        }
+
+        self.next();
+        let position = self.position();
+
+        match self.next().map(|t| t.kind) {
+            Some(TokenKind::Word(label)) => Ok(Some(label)),
+            Some(kind) => Err(position.error(format!(
+                "Expected an action label, found {}",
+                describe(&kind)
+            ))),
+            None => Err(position.error("Expected an action label, found end of file")),
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Expr>>> {
+        let mut alternatives = vec![self.sequence()?];
+
+        while self.peek_kind() == Some(&TokenKind::Pipe) {
+            self.next();
+            alternatives.push(self.sequence()?);
+        }
+
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Expr>> {
+        let mut sequence = Vec::new();
+
+        while !self.at_rule_start() {
+            let mut expr = match self.peek_kind() {
+                Some(TokenKind::Word(_))
+                | Some(TokenKind::Quoted(_))
+                | Some(TokenKind::Open)
+                | Some(TokenKind::OpenBracket) => self.primary()?,
+                _ => break,
+            };
+
+            loop {
+                expr = match self.peek_kind() {
+                    Some(TokenKind::Star) => Expr::Star(Box::new(expr)),
+                    Some(TokenKind::Plus) => Expr::Plus(Box::new(expr)),
+                    Some(TokenKind::Question) => Expr::Optional(Box::new(expr)),
+                    _ => break,
+                };
+                self.next();
+            }
+
+            sequence.push(expr);
+        }
+
+        Ok(sequence)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.next().unwrap();
+
+        match token.kind {
+            TokenKind::Word(word) => {
+                let symbol = symbol(&word, token.position)?;
+                if let Symbol::NonTerminal(nt) = &symbol {
+                    self.references.push((nt.clone(), token.position));
+                }
+                Ok(Expr::Symbol(symbol))
+            }
+            TokenKind::Quoted(terminal) => {
+                Ok(Expr::Symbol(Symbol::from_terminal(Terminal::new(terminal))))
+            }
+            TokenKind::Open => {
+                let group = self.alternatives()?;
+                self.expect(TokenKind::Close, "`)`")?;
+                Ok(Expr::Group(group))
+            }
+            TokenKind::OpenBracket => {
+                let group = self.alternatives()?;
+                self.expect(TokenKind::CloseBracket, "`]`")?;
+                Ok(Expr::Optional(Box::new(Expr::Group(group))))
+            }
+            _ => unreachable!("Only called on the start of a primary expression"),
+        }
+    }
+
+    fn expect(&mut self, expected: TokenKind, description: &str) -> Result<()> {
+        let position = self.position();
+
+        match self.next() {
+            Some(token) if token.kind == expected => Ok(()),
+            Some(token) => Err(position.error(format!(
+                "Expected {}, found {}",
+                description,
+                describe(&token.kind)
+            ))),
+            None => Err(position.error(format!("Expected {}, found end of file", description))),
+        }
+    }
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) => format!("{:?}", word),
+        TokenKind::Quoted(terminal) => format!("'{}'", terminal),
+        TokenKind::Arrow => String::from("`->`"),
+        TokenKind::Action => String::from("`=>`"),
+        TokenKind::Pipe => String::from("`|`"),
+        TokenKind::Open => String::from("`(`"),
+        TokenKind::Close => String::from("`)`"),
+        TokenKind::OpenBracket => String::from("`[`"),
+        TokenKind::CloseBracket => String::from("`]`"),
+        TokenKind::Star => String::from("`*`"),
+        TokenKind::Plus => String::from("`+`"),
+        TokenKind::Question => String::from("`?`"),
+    }
+}
+
+fn symbol(word: &str, position: Position) -> Result<Symbol> {
+    bare_symbol(word).ok_or_else(|| {
+        if word.contains('$') {
+            position.error(format!("Invalid symbol {:?}", word))
+        } else {
+            position.error(format!(
+                "Invalid symbol {:?}, symbols start with a letter",
+                word
+            ))
+        }
+    })
+}
+
+/// The symbol an unquoted word of a grammar file stands for: `lambda`, `$`,
+/// a non-terminal when it starts with an uppercase letter and a terminal when
+/// it starts with a lowercase one.
+pub(crate) fn bare_symbol(word: &str) -> Option<Symbol> {
+    let first = word.chars().next()?;
+
+    if !word.chars().all(is_word_char) {
+        None
+    } else if word == "lambda" {
+        Some(Symbol::Lambda)
+    } else if word == "$" {
+        Some(Symbol::from_terminal(Terminal::new(word)))
+    } else if word.contains('$') {
+        None
+    } else if first.is_uppercase() {
+        Some(Symbol::from_non_terminal(NonTerminal::new(word)))
+    } else if first.is_lowercase() {
+        Some(Symbol::from_terminal(Terminal::new(word)))
+    } else {
+        None
+    }
+}
+
+/// Parses a grammar file like `llre.cfg`.
+///
+/// A grammar is a list of rules `NT -> alternatives`, and the left hand side
+/// of the first rule is the start symbol. Newlines carry no meaning: a rule
+/// runs until the next `NT ->` or the end of the file, so alternatives can
+/// start a new line with `|` or share one. `#` starts a comment, terminals
+/// that contain punctuation can be quoted (`'('`), and right hand sides may
+/// use the EBNF operators understood by [`CFG::add_rule`]. An alternative can
+/// end with an action label, `ALT -> SEQ ALTLIST => alt`, which is stored in
+/// `CFG::actions`.
+pub fn parse(input: &str) -> Result<CFG> {
+    let (tokens, end) = Lexer::new(input).tokens()?;
+
+    let mut parser = Parser {
+        tokens,
+        index: 0,
+        end,
+        references: Vec::new(),
+    };
+
+    let mut rules = Vec::new();
+    while parser.peek().is_some() {
+        rules.push(parser.rule()?);
+    }
+
+    let mut cfg = CFG::new();
+
+    cfg.start_symbol = match rules.first() {
+        Some((start, _, _)) => start.clone(),
+        None => return Err(end.error("Cannot create an empty CFG")),
+    };
+
+    let defined: BTreeSet<&NonTerminal> = rules.iter().map(|(lhs, _, _)| lhs).collect();
+
+    if let Some((nt, position)) = parser
+        .references
+        .iter()
+        .find(|(nt, _)| !defined.contains(nt))
+    {
+        return Err(position.error(format!(
+            "Non-terminal {} has no productions",
+            nt.non_terminal()
+        )));
+    }
+
+    for (lhs, alternatives, actions) in &rules {
+        let indices = cfg.add_rule(lhs, alternatives);
+
+        for (idx, action) in indices.into_iter().zip(actions) {
+            if let Some(label) = action {
+                cfg.actions.insert(idx, label.clone());
+            }
+        }
+    }
+
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_at(input: &str) -> (usize, usize, String) {
+        match parse(input).unwrap_err().downcast::<Error>().unwrap() {
+            Error::GrammarParseError {
+                line,
+                column,
+                message,
+            } => (line, column, message),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    /// The alternatives of a right hand side on its own, as `rule` sees them.
+    fn alternatives(input: &str) -> Result<Vec<Vec<Expr>>> {
+        let (tokens, end) = Lexer::new(input).tokens()?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            end,
+            references: Vec::new(),
+        };
+        let alternatives = parser.alternatives()?;
+
+        match parser.next() {
+            Some(token) => Err(token
+                .position
+                .error(format!("Unexpected {}", describe(&token.kind)))),
+            None => Ok(alternatives),
+        }
+    }
+
+    #[test]
+    fn parse_operators() {
+        let sym = |s: &str| Expr::Symbol(Symbol::from_parse(s).unwrap());
+
+        assert_eq!(
+            alternatives("a (B | c)* [d] e+ | F?").unwrap(),
+            vec![
+                vec![
+                    sym("a"),
+                    Expr::Star(Box::new(Expr::Group(vec![vec![sym("B")], vec![sym("c")]]))),
+                    Expr::Optional(Box::new(Expr::Group(vec![vec![sym("d")]]))),
+                    Expr::Plus(Box::new(sym("e"))),
+                ],
+                vec![Expr::Optional(Box::new(sym("F")))],
+            ]
+        );
+
+        assert!(alternatives("(a").is_err());
+        assert!(alternatives("a ]").is_err());
+    }
+
+    #[test]
+    fn layout_and_comments() {
+        let input = "# A list of items\n\
+                     LIST\t->  item\tTAIL   # trailing comment\n\
+                     \n\
+                     TAIL -> ',' item TAIL\n\
+                     \t  | lambda\n\
+                     ITEM -> 'a|b' | '\\'' | \"->\"";
+
+        let cfg = parse(input).unwrap();
+        let terminals: Vec<&str> = cfg.terminals.iter().map(|t| t.terminal()).collect();
+
+        assert_eq!(cfg.start_symbol, NonTerminal::new("LIST"));
+        assert_eq!(terminals, vec!["'", ",", "->", "a|b", "item"]);
+        assert_eq!(cfg.productions.len(), 6);
+        assert_eq!(cfg.production_map[&NonTerminal::new("TAIL")], vec![1, 2]);
+        assert!(cfg.productions[2].only_lambda());
+    }
+
+    #[test]
+    fn same_as_line_format() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+        let one_line = parse(
+            "RE -> ALT $ ALT -> SEQ ALTLIST => alt \
+             ALTLIST -> pipe SEQ ALTLIST => alt_list | lambda => lambda \
+             SEQ -> ATOM SEQLIST => seq | lambda => seq \
+             SEQLIST -> ATOM SEQLIST => seq_list | lambda => lambda \
+             ATOM -> NUCLEUS ATOMMOD => atom ATOMMOD -> kleene | plus | lambda => lambda \
+             NUCLEUS -> open ALT close => group | char CHARRNG => range | dot \
+             CHARRNG -> dash char => range_end | lambda => lambda",
+        )
+        .unwrap();
+
+        assert_eq!(cfg, one_line);
+    }
+
+    #[test]
+    fn action_labels() {
+        let cfg = parse("LIST -> item LIST => cons | lambda => nil\nITEM -> (a | b)* => items | c")
+            .unwrap();
+
+        assert_eq!(cfg.actions.len(), 3);
+        assert_eq!(cfg.actions[&0], "cons");
+        assert_eq!(cfg.actions[&1], "nil");
+        assert_eq!(cfg.actions[&2], "items");
+        assert!(!cfg.actions.contains_key(&3));
+
+        assert_eq!(
+            error_at("A -> b =>\n| c"),
+            (2, 1, String::from("Expected an action label, found `|`"))
+        );
+        assert_eq!(error_at("A -> (b => x)").1, 9);
+    }
+
+    #[test]
+    fn diagnostics() {
+        assert_eq!(
+            error_at("A -> b\n  | c;"),
+            (
+                2,
+                6,
+                String::from("Unexpected character ';', quote terminals that contain punctuation")
+            )
+        );
+        assert_eq!(error_at("A -> (b | c\n").0, 2);
+        assert_eq!(error_at("A -> b ')").1, 8);
+        assert_eq!(error_at("a -> b").1, 1);
+        assert_eq!(
+            error_at("A -> B\nB -> C c"),
+            (2, 6, String::from("Non-terminal C has no productions"))
+        );
+        assert_eq!(error_at("A -> 'b\n'").0, 1);
+        assert_eq!(
+            error_at("# nothing\n"),
+            (2, 1, String::from("Cannot create an empty CFG"))
+        );
+    }
+}
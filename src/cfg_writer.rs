@@ -0,0 +1,348 @@
+use crate::cfg::CFG;
+use crate::symbol::{NonTerminal, Symbol};
+use anyhow::anyhow;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GrammarFormat {
+    /// The format read by `CFG::from_file`, like `llre.cfg`.
+    Cfg,
+    Bnf,
+    /// ISO 14977 EBNF.
+    Ebnf,
+    Markdown,
+    Latex,
+}
+
+impl FromStr for GrammarFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "cfg" => GrammarFormat::Cfg,
+            "bnf" => GrammarFormat::Bnf,
+            "ebnf" => GrammarFormat::Ebnf,
+            "markdown" | "md" => GrammarFormat::Markdown,
+            "latex" | "tex" => GrammarFormat::Latex,
+            _ => return Err(anyhow!("Unknown grammar format: {:?}", s)),
+        })
+    }
+}
+
+/// A `CFG` rendered in a particular format, see `CFG::display_as`.
+pub struct DisplayGrammar<'c> {
+    cfg: &'c CFG,
+    format: GrammarFormat,
+}
+
+impl CFG {
+    pub fn display_as(&self, format: GrammarFormat) -> DisplayGrammar<'_> {
+        DisplayGrammar { cfg: self, format }
+    }
+
+    pub fn write_as(&self, out: &mut dyn io::Write, format: GrammarFormat) -> io::Result<()> {
+        write!(out, "{}", self.display_as(format))
+    }
+
+    /// Non-terminals in the order they should be printed: the start symbol
+    /// first, then the rest by their first production.
+    fn rule_order(&self) -> Vec<&NonTerminal> {
+        let mut order = vec![&self.start_symbol];
+
+        for idx in 0..self.productions.len() {
+            if let Some(nt) = self.lhs(idx) {
+                if !order.contains(&nt) {
+                    order.push(nt);
+                }
+            }
+        }
+
+        order.retain(|nt| self.production_map.contains_key(*nt));
+        order
+    }
+
+    fn alternatives<'c>(&'c self, nt: &NonTerminal) -> impl Iterator<Item = &'c [Symbol]> + 'c {
+        self.production_map[nt]
+            .iter()
+            .map(move |idx| self.productions[*idx].symbols())
+    }
+}
+
+impl fmt::Display for CFG {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_as(GrammarFormat::Cfg))
+    }
+}
+
+impl fmt::Display for DisplayGrammar<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            GrammarFormat::Cfg => self.fmt_cfg(f),
+            GrammarFormat::Bnf => self.fmt_bnf(f),
+            GrammarFormat::Ebnf => self.fmt_ebnf(f),
+            GrammarFormat::Markdown => self.fmt_markdown(f),
+            GrammarFormat::Latex => self.fmt_latex(f),
+        }
+    }
+}
+
+impl DisplayGrammar<'_> {
+    fn fmt_cfg(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_aligned(f, "->", "|", true, |s| match s {
+            Symbol::Terminal(t) if is_bare_terminal(t.terminal()) => t.terminal().to_string(),
+            Symbol::Terminal(t) => quote(t.terminal(), '\''),
+            Symbol::NonTerminal(nt) => nt.non_terminal().to_string(),
+            Symbol::Lambda => String::from("lambda"),
+        })
+    }
+
+    fn fmt_bnf(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_aligned(f, "::=", "|", false, |s| match s {
+            Symbol::Terminal(t) => quote(t.terminal(), '"'),
+            Symbol::NonTerminal(nt) => format!("<{}>", nt.non_terminal()),
+            Symbol::Lambda => String::from("\"\""),
+        })
+    }
+
+    /// Writes one rule per non-terminal, with the left hand sides right
+    /// aligned and every further alternative on its own line.
+    fn fmt_aligned(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        arrow: &str,
+        pipe: &str,
+        actions: bool,
+        symbol: impl Fn(&Symbol) -> String,
+    ) -> fmt::Result {
+        let order = self.cfg.rule_order();
+        let names: Vec<String> = order
+            .iter()
+            .map(|nt| symbol(&Symbol::from_non_terminal((*nt).clone())))
+            .collect();
+        let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+        let indent = width + arrow.chars().count() - pipe.chars().count() + 1;
+
+        for (nt, name) in order.iter().zip(names.iter()) {
+            for (i, idx) in self.cfg.production_map[*nt].iter().enumerate() {
+                let mut rhs: Vec<String> = self.cfg.productions[*idx]
+                    .symbols()
+                    .iter()
+                    .map(&symbol)
+                    .collect();
+
+                if let Some(label) = self.cfg.actions.get(idx).filter(|_| actions) {
+                    rhs.push(format!("=> {}", label));
+                }
+
+                if i == 0 {
+                    writeln!(f, "{:>w$} {} {}", name, arrow, rhs.join(" "), w = width)?;
+                } else {
+                    writeln!(f, "{:w$}{} {}", "", pipe, rhs.join(" "), w = indent)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fmt_ebnf(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = self.cfg.rule_order();
+        let width = order
+            .iter()
+            .map(|nt| nt.non_terminal().chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for nt in order {
+            for (i, symbols) in self.cfg.alternatives(nt).enumerate() {
+                let rhs: Vec<String> = symbols
+                    .iter()
+                    .filter(|s| !s.is_lambda())
+                    .map(|s| match s {
+                        Symbol::Terminal(t) => quote(t.terminal(), '"'),
+                        other => other.as_str().to_string(),
+                    })
+                    .collect();
+
+                if i == 0 {
+                    write!(
+                        f,
+                        "{:>w$} = {}",
+                        nt.non_terminal(),
+                        rhs.join(", "),
+                        w = width
+                    )?;
+                } else {
+                    write!(f, "\n{:w$} | {}", "", rhs.join(", "), w = width)?;
+                }
+            }
+
+            writeln!(f, " ;")?;
+        }
+
+        Ok(())
+    }
+
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "| # | Non-terminal | Production |")?;
+        writeln!(f, "|--:|--------------|------------|")?;
+
+        for nt in self.cfg.rule_order() {
+            for idx in &self.cfg.production_map[nt] {
+                let rhs: Vec<String> = self.cfg.productions[*idx]
+                    .symbols()
+                    .iter()
+                    .map(|s| match s {
+                        Symbol::Terminal(t) => format!("`{}`", t.terminal().replace('|', "\\|")),
+                        Symbol::NonTerminal(nt) => nt.non_terminal().to_string(),
+                        Symbol::Lambda => String::from("λ"),
+                    })
+                    .collect();
+
+                writeln!(f, "| {} | {} | {} |", idx, nt.non_terminal(), rhs.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fmt_latex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let latex = |s: &Symbol| match s {
+            Symbol::Terminal(t) => format!("\\texttt{{{}}}", escape_latex(t.terminal())),
+            Symbol::NonTerminal(nt) => format!("\\mathit{{{}}}", escape_latex(nt.non_terminal())),
+            Symbol::Lambda => String::from("\\lambda"),
+        };
+
+        writeln!(f, "\\begin{{align*}}")?;
+
+        for nt in self.cfg.rule_order() {
+            for (i, symbols) in self.cfg.alternatives(nt).enumerate() {
+                let rhs: Vec<String> = symbols.iter().map(latex).collect();
+
+                if i == 0 {
+                    write!(
+                        f,
+                        "{} &\\rightarrow {}",
+                        latex(&Symbol::from_non_terminal(nt.clone())),
+                        rhs.join("\\ ")
+                    )?;
+                } else {
+                    write!(f, " \\\\\n  &\\mid {}", rhs.join("\\ "))?;
+                }
+            }
+
+            writeln!(f, " \\\\")?;
+        }
+
+        writeln!(f, "\\end{{align*}}")
+    }
+}
+
+/// Whether `cfg_parser` reads `terminal` back without quotes.
+fn is_bare_terminal(terminal: &str) -> bool {
+    terminal == "$"
+        || (terminal != "lambda"
+            && terminal.chars().next().is_some_and(char::is_lowercase)
+            && terminal.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+fn quote(s: &str, quote: char) -> String {
+    let mut quoted = quote.to_string();
+
+    for c in s.chars() {
+        if c == quote || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    quoted.push(quote);
+    quoted
+}
+
+fn escape_latex(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\\' => String::from("\\textbackslash{}"),
+            '~' => String::from("\\textasciitilde{}"),
+            '^' => String::from("\\textasciicircum{}"),
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_regex_grammar() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+
+        let expected = "     RE -> ALT $
+    ALT -> SEQ ALTLIST => alt
+ALTLIST -> pipe SEQ ALTLIST => alt_list
+         | lambda => lambda
+    SEQ -> ATOM SEQLIST => seq
+         | lambda => seq
+SEQLIST -> ATOM SEQLIST => seq_list
+         | lambda => lambda
+   ATOM -> NUCLEUS ATOMMOD => atom
+ATOMMOD -> kleene
+         | plus
+         | lambda => lambda
+NUCLEUS -> open ALT close => group
+         | char CHARRNG => range
+         | dot
+CHARRNG -> dash char => range_end
+         | lambda => lambda
+";
+
+        assert_eq!(cfg.to_string(), expected);
+        assert_eq!(cfg.to_string().parse::<CFG>().unwrap(), cfg);
+    }
+
+    #[test]
+    fn round_trip_transformed() {
+        let cnf = CFG::from_file("llre.cfg").unwrap().to_cnf();
+        let printed = cnf.to_string();
+
+        assert_eq!(printed.parse::<CFG>().unwrap().to_string(), printed);
+
+        let quoted: CFG = "E -> T ('+' T)* T -> '\\'' | \"lambda\" | '$x'"
+            .parse()
+            .unwrap();
+        assert_eq!(quoted.to_string().parse::<CFG>().unwrap(), quoted);
+    }
+
+    #[test]
+    fn other_formats() {
+        let cfg: CFG = "LIST -> item ',' LIST | lambda".parse().unwrap();
+
+        assert_eq!(
+            cfg.display_as(GrammarFormat::Bnf).to_string(),
+            "<LIST> ::= \"item\" \",\" <LIST>\n         | \"\"\n"
+        );
+        assert_eq!(
+            cfg.display_as(GrammarFormat::Ebnf).to_string(),
+            "LIST = \"item\", \",\", LIST\n     |  ;\n"
+        );
+        assert_eq!(
+            cfg.display_as(GrammarFormat::Markdown).to_string(),
+            "| # | Non-terminal | Production |\n\
+             |--:|--------------|------------|\n\
+             | 0 | LIST | `item` `,` LIST |\n\
+             | 1 | LIST | λ |\n"
+        );
+        assert_eq!(
+            cfg.display_as(GrammarFormat::Latex).to_string(),
+            "\\begin{align*}\n\
+             \\mathit{LIST} &\\rightarrow \\texttt{item}\\ \\texttt{,}\\ \\mathit{LIST} \\\\\n  \
+             &\\mid \\lambda \\\\\n\
+             \\end{align*}\n"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+use std::iter::FromIterator;
+
+/// A set of characters stored as sorted, disjoint and non-adjacent
+/// intervals, so a range like `a-z` or all of Unicode costs one entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharSet {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn single(c: char) -> Self {
+        Self::range(c, c)
+    }
+
+    /// The characters from `start` to `end` inclusive, empty when `end`
+    /// comes before `start`.
+    pub fn range(start: char, end: char) -> Self {
+        if start > end {
+            return Self::new();
+        }
+
+        Self {
+            ranges: vec![(start, end)],
+        }
+    }
+
+    /// Every Unicode scalar value.
+    pub fn all() -> Self {
+        Self::range('\0', char::MAX)
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    /// The number of characters in the set.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| {
+                let surrogates = if start <= '\u{D7FF}' && end >= '\u{E000}' {
+                    0x800
+                } else {
+                    0
+                };
+
+                (end as usize - start as usize + 1) - surrogates
+            })
+            .sum()
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.ranges.iter().flat_map(|&(start, end)| start..=end)
+    }
+
+    pub fn union(&self, other: &CharSet) -> CharSet {
+        let mut ranges = self.ranges.clone();
+        ranges.extend_from_slice(&other.ranges);
+        Self::normalize(ranges)
+    }
+
+    pub fn intersection(&self, other: &CharSet) -> CharSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a_start, a_end) = self.ranges[i];
+            let (b_start, b_end) = other.ranges[j];
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+
+            if start <= end {
+                ranges.push((start, end));
+            }
+
+            if a_end < b_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { ranges }
+    }
+
+    pub fn complement(&self) -> CharSet {
+        let mut ranges = Vec::new();
+        let mut next = Some('\0');
+
+        for &(start, end) in &self.ranges {
+            if let (Some(from), Some(to)) = (next, prev_char(start)) {
+                if from <= to {
+                    ranges.push((from, to));
+                }
+            }
+
+            next = next_char(end);
+        }
+
+        if let Some(from) = next {
+            ranges.push((from, char::MAX));
+        }
+
+        Self { ranges }
+    }
+
+    pub fn difference(&self, other: &CharSet) -> CharSet {
+        self.intersection(&other.complement())
+    }
+
+    fn normalize(mut ranges: Vec<(char, char)>) -> CharSet {
+        ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last)) if next_char(*last).is_none_or(|n| n >= start) => {
+                    *last = (*last).max(end)
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+}
+
+/// The character after `c`, skipping the surrogates that are not `char`s.
+pub fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        c => std::char::from_u32(c as u32 + 1),
+    }
+}
+
+/// The character before `c`, skipping the surrogates that are not `char`s.
+pub fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\0' => None,
+        '\u{E000}' => Some('\u{D7FF}'),
+        c => std::char::from_u32(c as u32 - 1),
+    }
+}
+
+impl FromIterator<char> for CharSet {
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        Self::normalize(chars.into_iter().map(|c| (c, c)).collect())
+    }
+}
+
+impl<'a> FromIterator<&'a char> for CharSet {
+    fn from_iter<I: IntoIterator<Item = &'a char>>(chars: I) -> Self {
+        chars.into_iter().copied().collect()
+    }
+}
+
+impl fmt::Display for CharSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+
+        for &(start, end) in &self.ranges {
+            if start == end {
+                write!(f, "{}", start.escape_debug())?;
+            } else {
+                write!(f, "{}-{}", start.escape_debug(), end.escape_debug())?;
+            }
+        }
+
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized() {
+        let set: CharSet = "dcbaxz".chars().collect();
+        assert_eq!(set.ranges(), &[('a', 'd'), ('x', 'x'), ('z', 'z')]);
+        assert_eq!(set.len(), 6);
+        assert!(set.contains('c') && set.contains('z') && !set.contains('y'));
+        assert!(CharSet::range('b', 'a').is_empty());
+
+        let overlapping = CharSet::range('a', 'f').union(&CharSet::range('c', 'k'));
+        assert_eq!(overlapping, CharSet::range('a', 'k'));
+        assert_eq!(set.to_string(), "[a-dxz]");
+    }
+
+    #[test]
+    fn operations() {
+        let lower = CharSet::range('a', 'z');
+        let hex: CharSet = CharSet::range('0', '9').union(&CharSet::range('a', 'f'));
+
+        assert_eq!(lower.intersection(&hex), CharSet::range('a', 'f'));
+        assert_eq!(lower.difference(&hex), CharSet::range('g', 'z'));
+        assert_eq!(hex.difference(&lower), CharSet::range('0', '9'));
+        assert_eq!(lower.complement().complement(), lower);
+        assert_eq!(CharSet::new().complement(), CharSet::all());
+        assert!(CharSet::all().complement().is_empty());
+        assert_eq!(CharSet::all().len(), 0x110000 - 0x800);
+    }
+
+    #[test]
+    fn surrogates() {
+        let around = CharSet::single('\u{D7FF}').union(&CharSet::single('\u{E000}'));
+        assert_eq!(around, CharSet::range('\u{D7FF}', '\u{E000}'));
+        assert_eq!(around.len(), 2);
+        assert_eq!(
+            around.complement().ranges(),
+            &[('\0', '\u{D7FE}'), ('\u{E001}', char::MAX)]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::alphabet_translator::alphabet_translator;
+use crate::char_set::CharSet;
+use crate::dfa::Dfa;
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// A partition of an alphabet into classes of characters that every
+/// transition of a set of automata treats the same, so a transition table
+/// needs one column per class instead of one per character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharClasses {
+    /// Disjoint and sorted by their first character.
+    classes: Vec<CharSet>,
+}
+
+impl CharClasses {
+    /// Splits `alphabet` until no label of `dfas` separates two characters of
+    /// the same class.
+    pub fn new(alphabet: &BTreeSet<char>, dfas: &[Dfa]) -> Self {
+        let labels: BTreeSet<&CharSet> = dfas
+            .iter()
+            .flat_map(|dfa| &dfa.states)
+            .flat_map(|state| &state.transitions)
+            .map(|(label, _)| label)
+            .collect();
+
+        let alphabet: CharSet = alphabet.iter().collect();
+        let mut classes = vec![];
+
+        if !alphabet.is_empty() {
+            classes.push(alphabet);
+        }
+
+        for label in labels {
+            classes = classes
+                .into_iter()
+                .flat_map(|class| vec![class.intersection(label), class.difference(label)])
+                .filter(|class| !class.is_empty())
+                .collect();
+        }
+
+        classes.sort_by_key(|class| class.ranges()[0]);
+
+        Self { classes }
+    }
+
+    pub fn classes(&self) -> &[CharSet] {
+        &self.classes
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// A class for each character of `alphabet`, for tables indexed by
+    /// character.
+    pub fn singletons(alphabet: &BTreeSet<char>) -> Self {
+        Self {
+            classes: alphabet.iter().map(|&c| CharSet::single(c)).collect(),
+        }
+    }
+
+    /// The class of `c`, `None` when it is not in the alphabet.
+    pub fn class_of(&self, c: char) -> Option<usize> {
+        self.classes.iter().position(|class| class.contains(c))
+    }
+
+    /// Writes the char to class map, one class per line: its index and then
+    /// its characters.
+    pub fn write(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        for (i, class) in self.classes.iter().enumerate() {
+            write!(out, "{}", i)?;
+
+            for c in class.chars() {
+                write!(
+                    out,
+                    " {}",
+                    alphabet_translator::char_to_hex_if_whitespace(c)
+                )?;
+            }
+
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Dfa {
+    /// The transition table of the DFA with one column per class of
+    /// `classes`, `None` where the DFA has no transition.
+    pub fn table(&self, classes: &CharClasses) -> Vec<Vec<Option<usize>>> {
+        (0..self.states.len())
+            .map(|state| {
+                classes
+                    .classes()
+                    .iter()
+                    .map(|class| self.next(state, class.ranges()[0].0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Writes the `.tt` table of the DFA indexed by class, see `write_rows`.
+    pub fn write_table(&self, out: &mut dyn Write, classes: &CharClasses) -> std::io::Result<()> {
+        let accepting: Vec<bool> = self.states.iter().map(|state| state.accepting).collect();
+        write_rows(out, &self.table(classes), &accepting)
+    }
+}
+
+/// Writes a dense `.tt` table: a line per state of `+` or `-` for whether it
+/// accepts, its number, and then the next state for each column, or `E` for
+/// an error.
+pub fn write_rows(
+    out: &mut dyn Write,
+    rows: &[Vec<Option<usize>>],
+    accepting: &[bool],
+) -> std::io::Result<()> {
+    for (state, row) in rows.iter().enumerate() {
+        let accepting = if accepting[state] { '+' } else { '-' };
+        write!(out, "{} {}", accepting, state)?;
+
+        for next in row {
+            match next {
+                Some(next) => write!(out, " {}", next)?,
+                None => write!(out, " E")?,
+            }
+        }
+
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dfa(regex: &str, alphabet: &BTreeSet<char>) -> Dfa {
+        Dfa::from_regex(&regex.parse().unwrap(), alphabet)
+    }
+
+    #[test]
+    fn classes() {
+        let alphabet: BTreeSet<char> = "abcdefghij0123456789 ".chars().collect();
+        let dfas: Vec<Dfa> = ["a-j+", "0-9+", "if", ". *"]
+            .iter()
+            .map(|regex| dfa(regex, &alphabet))
+            .collect();
+        let classes = CharClasses::new(&alphabet, &dfas);
+
+        let class: Vec<String> = classes.classes().iter().map(|c| c.to_string()).collect();
+        assert_eq!(class, vec!["[ ]", "[0-9]", "[a-eg-hj]", "[f]", "[i]"]);
+        assert_eq!(classes.class_of('7'), Some(1));
+        assert_eq!(classes.class_of('z'), None);
+
+        for dfa in &dfas {
+            let table = dfa.table(&classes);
+
+            for (state, row) in table.iter().enumerate() {
+                for &c in &alphabet {
+                    assert_eq!(row[classes.class_of(c).unwrap()], dfa.next(state, c));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn written() {
+        let alphabet: BTreeSet<char> = "ab ".chars().collect();
+        let dfas = vec![dfa("a+b", &alphabet), dfa(". ", &alphabet)];
+        let classes = CharClasses::new(&alphabet, &dfas);
+
+        let mut map = Vec::new();
+        classes.write(&mut map).unwrap();
+        assert_eq!(String::from_utf8(map).unwrap(), "0 x20\n1 a\n2 b\n");
+
+        let mut table = Vec::new();
+        dfas[0].write_table(&mut table, &classes).unwrap();
+        assert_eq!(
+            String::from_utf8(table).unwrap(),
+            "- 0 E 1 E\n- 1 E 1 2\n+ 2 E E E\n"
+        );
+    }
+}
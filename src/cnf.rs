@@ -0,0 +1,272 @@
+use crate::cfg::CFG;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use std::collections::{BTreeMap, BTreeSet};
+
+type Rule = (NonTerminal, Vec<Symbol>);
+
+impl CFG {
+    /// Converts the grammar into Chomsky normal form. Every production of the
+    /// result is either `A -> B C` or `A -> a`, and only the (fresh) start
+    /// symbol may derive lambda.
+    pub fn to_cnf(&self) -> CFG {
+        // Used only to hand out non-terminal names that don't collide.
+        let mut names = self.clone();
+
+        let start = names.fresh_non_terminal(self.start_symbol.non_terminal());
+        names.non_terminals.insert(start.clone());
+
+        let mut rules = vec![(
+            start.clone(),
+            vec![Symbol::from_non_terminal(self.start_symbol.clone())],
+        )];
+        rules.extend(self.rules());
+
+        let rules = lift_terminals(rules, &mut names);
+        let rules = binarize(rules, &mut names);
+        let rules = remove_lambda(rules, &start);
+        let rules = remove_units(rules);
+        let rules = remove_unreachable(rules, &start);
+
+        build(&start, rules)
+    }
+
+    /// Whether every production is `A -> B C`, `A -> a`, or `S -> lambda`
+    /// for the start symbol `S`, which must not appear on a right hand side.
+    pub fn is_cnf(&self) -> bool {
+        self.rules().all(|(lhs, symbols)| match symbols.as_slice() {
+            [Symbol::Terminal(_)] => true,
+            [Symbol::Lambda] => lhs == self.start_symbol,
+            [Symbol::NonTerminal(b), Symbol::NonTerminal(c)] => {
+                *b != self.start_symbol && *c != self.start_symbol
+            }
+            _ => false,
+        })
+    }
+
+    fn rules(&self) -> impl Iterator<Item = Rule> + '_ {
+        self.production_map.iter().flat_map(move |(nt, indices)| {
+            indices
+                .iter()
+                .map(move |idx| (nt.clone(), self.productions[*idx].symbols().to_vec()))
+        })
+    }
+}
+
+fn build(start: &NonTerminal, rules: Vec<Rule>) -> CFG {
+    let mut cfg = CFG::new();
+    cfg.start_symbol = start.clone();
+    cfg.non_terminals.insert(start.clone());
+
+    for (lhs, symbols) in rules {
+        cfg.add_production(lhs, symbols);
+    }
+
+    cfg
+}
+
+fn push_unique(rules: &mut Vec<Rule>, rule: Rule) {
+    if !rules.contains(&rule) {
+        rules.push(rule);
+    }
+}
+
+/// Replaces terminals inside productions of length two or more with a new
+/// non-terminal `T_x -> x`.
+fn lift_terminals(mut rules: Vec<Rule>, names: &mut CFG) -> Vec<Rule> {
+    let mut lifted: BTreeMap<Terminal, NonTerminal> = BTreeMap::new();
+
+    for (_, symbols) in rules.iter_mut().filter(|(_, s)| s.len() >= 2) {
+        for symbol in symbols.iter_mut() {
+            if let Symbol::Terminal(t) = symbol {
+                let nt = lifted
+                    .entry(t.clone())
+                    .or_insert_with(|| {
+                        let upper: String = t
+                            .terminal()
+                            .chars()
+                            .filter(|c| c.is_ascii_alphanumeric())
+                            .collect::<String>()
+                            .to_uppercase();
+                        let base = if upper.is_empty() {
+                            String::from("T")
+                        } else {
+                            format!("T_{}", upper)
+                        };
+
+                        let nt = names.fresh_non_terminal(&base);
+                        names.non_terminals.insert(nt.clone());
+                        nt
+                    })
+                    .clone();
+
+                *symbol = Symbol::from_non_terminal(nt);
+            }
+        }
+    }
+
+    for (t, nt) in lifted {
+        rules.push((nt, vec![Symbol::from_terminal(t)]));
+    }
+
+    rules
+}
+
+/// Splits `A -> X1 X2 ... Xn` into a chain of productions with two symbols each.
+fn binarize(rules: Vec<Rule>, names: &mut CFG) -> Vec<Rule> {
+    let mut binary = Vec::new();
+
+    for (lhs, symbols) in rules {
+        if symbols.len() <= 2 {
+            binary.push((lhs, symbols));
+            continue;
+        }
+
+        let (head, tail) = symbols.split_at(symbols.len() - 2);
+        let mut current = lhs.clone();
+
+        for symbol in head {
+            let next = names.fresh_non_terminal(lhs.non_terminal());
+            names.non_terminals.insert(next.clone());
+
+            binary.push((
+                current,
+                vec![symbol.clone(), Symbol::from_non_terminal(next.clone())],
+            ));
+            current = next;
+        }
+
+        binary.push((current, tail.to_vec()));
+    }
+
+    binary
+}
+
+/// Removes all lambda productions, adding every variant of a production with
+/// its nullable symbols left out. The start symbol keeps `S -> lambda` if the
+/// language contains the empty string.
+fn remove_lambda(rules: Vec<Rule>, start: &NonTerminal) -> Vec<Rule> {
+    let nullable = build(start, rules.clone()).nullable();
+    let mut result = Vec::new();
+
+    for (lhs, symbols) in rules {
+        let mut variants: Vec<Vec<Symbol>> = vec![Vec::new()];
+
+        for symbol in symbols.into_iter().filter(|s| !s.is_lambda()) {
+            let optional = match &symbol {
+                Symbol::NonTerminal(nt) => nullable.contains(nt),
+                _ => false,
+            };
+
+            let mut with_symbol: Vec<Vec<Symbol>> = variants
+                .iter()
+                .cloned()
+                .map(|mut v| {
+                    v.push(symbol.clone());
+                    v
+                })
+                .collect();
+
+            if optional {
+                variants.append(&mut with_symbol);
+            } else {
+                variants = with_symbol;
+            }
+        }
+
+        for variant in variants.into_iter().filter(|v| !v.is_empty()) {
+            push_unique(&mut result, (lhs.clone(), variant));
+        }
+    }
+
+    if nullable.contains(start) {
+        push_unique(&mut result, (start.clone(), vec![Symbol::Lambda]));
+    }
+
+    result
+}
+
+/// Replaces unit productions `A -> B` with the non-unit productions of every
+/// non-terminal reachable from `A` through unit productions.
+fn remove_units(rules: Vec<Rule>) -> Vec<Rule> {
+    let is_unit = |symbols: &[Symbol]| symbols.len() == 1 && symbols[0].is_non_terminal();
+
+    let mut order: Vec<&NonTerminal> = Vec::new();
+    for (lhs, _) in &rules {
+        if !order.contains(&lhs) {
+            order.push(lhs);
+        }
+    }
+
+    let mut result = Vec::new();
+
+    for nt in order {
+        let mut closure = vec![nt];
+        let mut i = 0;
+
+        while i < closure.len() {
+            for (lhs, symbols) in &rules {
+                if lhs == closure[i] && is_unit(symbols) {
+                    let target = symbols[0].non_terminal().unwrap();
+                    if !closure.contains(&target) {
+                        closure.push(target);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        for reachable in closure {
+            for (lhs, symbols) in &rules {
+                if lhs == reachable && !is_unit(symbols) {
+                    push_unique(&mut result, (nt.clone(), symbols.clone()));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn remove_unreachable(rules: Vec<Rule>, start: &NonTerminal) -> Vec<Rule> {
+    let mut reachable = BTreeSet::new();
+    let mut stack = vec![start];
+
+    while let Some(nt) = stack.pop() {
+        if !reachable.insert(nt) {
+            continue;
+        }
+
+        for (lhs, symbols) in &rules {
+            if lhs == nt {
+                stack.extend(symbols.iter().filter_map(|s| s.non_terminal().ok()));
+            }
+        }
+    }
+
+    let reachable: BTreeSet<NonTerminal> = reachable.into_iter().cloned().collect();
+
+    rules
+        .into_iter()
+        .filter(|(lhs, _)| reachable.contains(lhs))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_grammar_to_cnf() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+        assert!(!cfg.is_cnf());
+
+        let cnf = cfg.to_cnf();
+        assert!(cnf.is_cnf());
+        assert_eq!(cnf.start_symbol, NonTerminal::new("RE_1"));
+
+        // `RE -> ALT $` always needs the end marker, so lambda is not in the language.
+        assert!(cnf.production_map[&cnf.start_symbol]
+            .iter()
+            .all(|idx| !cnf.productions[*idx].only_lambda()));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::cfg::CFG;
+use crate::ll_table::LLTable;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Writes a standalone Rust module with a recursive-descent parser for `cfg`,
+/// one function per non-terminal that dispatches on the cells of `table`.
+///
+/// The module has a `Terminal` enum to tag tokens with, where `End` stands for
+/// `$` and matches the end of the input, and a `parse` function that turns
+/// `(Terminal, T)` tokens into a `Tree<T>`:
+///
+/// ```
+/// # use wreck::{cfg::CFG, codegen::write_parser, ll_table::LLTable};
+/// let cfg: CFG = "LIST -> item LIST | lambda".parse().unwrap();
+/// let mut source = Vec::new();
+/// write_parser(&cfg, &LLTable::from_cfg(&cfg), &mut source).unwrap();
+///
+/// let source = String::from_utf8(source).unwrap();
+/// assert!(source.contains("fn parse_list(&mut self)"));
+/// ```
+pub fn write_parser(cfg: &CFG, table: &LLTable<'_>, out: &mut dyn Write) -> io::Result<()> {
+    let names = Names::new(cfg);
+
+    writeln!(out, "// Generated by WRECK, do not edit.\n")?;
+    writeln!(out, "use std::fmt;")?;
+    writeln!(out, "use std::iter::Peekable;\n")?;
+
+    writeln!(
+        out,
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]"
+    )?;
+    writeln!(out, "pub enum Terminal {{")?;
+    for (terminal, variant) in &names.terminals {
+        writeln!(out, "    /// `{}`", terminal.terminal())?;
+        writeln!(out, "    {},", variant)?;
+    }
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "impl Terminal {{")?;
+    writeln!(out, "    pub fn name(self) -> &'static str {{")?;
+    writeln!(out, "        match self {{")?;
+    for (terminal, variant) in &names.terminals {
+        writeln!(
+            out,
+            "            Terminal::{} => {:?},",
+            variant,
+            terminal.terminal()
+        )?;
+    }
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}\n")?;
+    writeln!(out, "    pub fn from_name(name: &str) -> Option<Self> {{")?;
+    writeln!(out, "        match name {{")?;
+    for (terminal, variant) in &names.terminals {
+        writeln!(
+            out,
+            "            {:?} => Some(Terminal::{}),",
+            terminal.terminal(),
+            variant
+        )?;
+    }
+    writeln!(out, "            _ => None,")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(
+        out,
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]"
+    )?;
+    writeln!(out, "pub enum NonTerminal {{")?;
+    for (variant, _) in names.non_terminals.values() {
+        writeln!(out, "    {},", variant)?;
+    }
+    writeln!(out, "}}\n")?;
+
+    out.write_all(RUNTIME.as_bytes())?;
+
+    let start = &names.non_terminals[&cfg.start_symbol].1;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "pub fn parse<T>(tokens: impl IntoIterator<Item = (Terminal, T)>) -> Result<Tree<T>, SyntaxError> {{"
+    )?;
+    writeln!(out, "    let mut parser = Parser {{")?;
+    writeln!(out, "        tokens: tokens.into_iter().peekable(),")?;
+    writeln!(out, "        position: 0,")?;
+    writeln!(out, "    }};\n")?;
+    writeln!(out, "    let tree = parser.{}()?;", start)?;
+    writeln!(out, "    parser.expect_end()?;")?;
+    writeln!(out, "    Ok(tree)")?;
+    writeln!(out, "}}\n")?;
+
+    writeln!(
+        out,
+        "impl<T, I: Iterator<Item = (Terminal, T)>> Parser<T, I> {{"
+    )?;
+    for (i, nt) in cfg.non_terminals.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        write_non_terminal(cfg, table, &names, nt, out)?;
+    }
+    writeln!(out, "}}")
+}
+
+fn write_non_terminal(
+    cfg: &CFG,
+    table: &LLTable<'_>,
+    names: &Names,
+    nt: &NonTerminal,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let (variant, function) = &names.non_terminals[nt];
+    let mut predicted: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+
+    for (terminal, cell) in &table.table[nt] {
+        if let Some(production) = cell {
+            predicted
+                .entry(*production)
+                .or_default()
+                .push(&names.terminals[terminal]);
+        }
+    }
+
+    writeln!(
+        out,
+        "    fn {}(&mut self) -> Result<Tree<T>, SyntaxError> {{",
+        function
+    )?;
+    writeln!(out, "        match self.peek() {{")?;
+
+    for idx in &cfg.production_map[nt] {
+        let terminals = match predicted.get(idx) {
+            Some(terminals) => terminals,
+            None => continue,
+        };
+
+        let pattern: Vec<String> = terminals
+            .iter()
+            .map(|t| format!("Terminal::{}", t))
+            .collect();
+        writeln!(out, "            {} => {{", pattern.join(" | "))?;
+        writeln!(
+            out,
+            "                // {} -> {}",
+            nt.non_terminal(),
+            cfg.productions[*idx].to_string().trim_end()
+        )?;
+
+        let mut children = Vec::new();
+        for symbol in cfg.productions[*idx].symbols() {
+            let child = format!("c{}", children.len());
+
+            match symbol {
+                Symbol::Terminal(t) if t.terminal() == "$" => {
+                    writeln!(out, "                self.expect_end()?;")?;
+                }
+                Symbol::Terminal(t) => {
+                    let call = format!("self.expect(Terminal::{})?", names.terminals[t]);
+                    writeln!(out, "                let {} = {};", child, call)?;
+                    children.push(child);
+                }
+                Symbol::NonTerminal(n) => {
+                    let call = format!("self.{}()?", names.non_terminals[n].1);
+                    writeln!(out, "                let {} = {};", child, call)?;
+                    children.push(child);
+                }
+                Symbol::Lambda => {}
+            }
+        }
+
+        writeln!(
+            out,
+            "                Ok(Tree::node(NonTerminal::{}, {}, vec![{}]))",
+            variant,
+            idx,
+            children.join(", ")
+        )?;
+        writeln!(out, "            }}")?;
+    }
+
+    let expected: BTreeSet<&str> = predicted.values().flatten().copied().collect();
+
+    // A row that predicts every terminal has no syntax errors.
+    if expected.len() < names.terminals.len() {
+        let expected: Vec<String> = expected
+            .iter()
+            .map(|t| format!("Terminal::{}", t))
+            .collect();
+
+        writeln!(
+            out,
+            "            _ => Err(self.error(&[{}])),",
+            expected.join(", ")
+        )?;
+    }
+
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")
+}
+
+/// Rust identifiers for the symbols of a grammar.
+struct Names {
+    /// Enum variants, with `$` as `End`.
+    terminals: BTreeMap<Terminal, String>,
+    /// Enum variants and parse functions.
+    non_terminals: BTreeMap<NonTerminal, (String, String)>,
+}
+
+impl Names {
+    fn new(cfg: &CFG) -> Self {
+        let mut used = BTreeSet::new();
+        let mut terminals = BTreeMap::new();
+
+        terminals.insert(Terminal::new("$"), unique(&mut used, "End"));
+        for (i, terminal) in cfg.terminals.iter().enumerate() {
+            if terminal.terminal() != "$" {
+                let name = camel_case(terminal.terminal()).unwrap_or_else(|| format!("T{}", i));
+                terminals.insert(terminal.clone(), unique(&mut used, &name));
+            }
+        }
+
+        let mut used = BTreeSet::new();
+        let mut functions = BTreeSet::new();
+        let non_terminals = cfg
+            .non_terminals
+            .iter()
+            .enumerate()
+            .map(|(i, nt)| {
+                let name = camel_case(nt.non_terminal()).unwrap_or_else(|| format!("N{}", i));
+                let variant = unique(&mut used, &name);
+                let function = unique(&mut functions, &format!("parse_{}", snake_case(&variant)));
+                (nt.clone(), (variant, function))
+            })
+            .collect();
+
+        Self {
+            terminals,
+            non_terminals,
+        }
+    }
+}
+
+/// `char_class` and `CHAR_CLASS` become `CharClass`. Returns `None` for
+/// names that contain anything but ASCII letters, digits and underscores.
+fn camel_case(name: &str) -> Option<String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let camel: String = name
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first)
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+                .collect::<String>()
+        })
+        .collect();
+
+    match camel.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => Some(camel),
+        _ => None,
+    }
+}
+
+fn snake_case(camel: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in camel.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
+
+fn unique(used: &mut BTreeSet<String>, name: &str) -> String {
+    let name = (0..)
+        .map(|n| {
+            if n == 0 {
+                name.to_string()
+            } else {
+                format!("{}{}", name, n)
+            }
+        })
+        .find(|n| !used.contains(n))
+        .unwrap();
+
+    used.insert(name.clone());
+    name
+}
+
+/// The grammar independent part of every generated parser.
+const RUNTIME: &str = r#"#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree<T> {
+    Leaf(T),
+    Node {
+        non_terminal: NonTerminal,
+        production: usize,
+        children: Vec<Tree<T>>,
+    },
+}
+
+impl<T> Tree<T> {
+    fn node(non_terminal: NonTerminal, production: usize, children: Vec<Tree<T>>) -> Self {
+        Tree::Node {
+            non_terminal,
+            production,
+            children,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The index of the offending token.
+    pub position: usize,
+    pub expected: Vec<Terminal>,
+    pub found: Terminal,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected: Vec<&str> = self.expected.iter().map(|t| t.name()).collect();
+
+        write!(
+            f,
+            "Syntax error at token {}: expected one of {}, found {}",
+            self.position,
+            expected.join(", "),
+            self.found.name()
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+struct Parser<T, I: Iterator<Item = (Terminal, T)>> {
+    tokens: Peekable<I>,
+    position: usize,
+}
+
+impl<T, I: Iterator<Item = (Terminal, T)>> Parser<T, I> {
+    fn peek(&mut self) -> Terminal {
+        self.tokens.peek().map_or(Terminal::End, |(t, _)| *t)
+    }
+
+    fn expect(&mut self, terminal: Terminal) -> Result<Tree<T>, SyntaxError> {
+        match self.tokens.next_if(|(t, _)| *t == terminal) {
+            Some((_, token)) => {
+                self.position += 1;
+                Ok(Tree::Leaf(token))
+            }
+            None => Err(self.error(&[terminal])),
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), SyntaxError> {
+        match self.peek() {
+            Terminal::End => Ok(()),
+            _ => Err(self.error(&[Terminal::End])),
+        }
+    }
+
+    fn error(&mut self, expected: &[Terminal]) -> SyntaxError {
+        SyntaxError {
+            position: self.position,
+            expected: expected.to_vec(),
+            found: self.peek(),
+        }
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    mod llre {
+        include!(concat!(env!("OUT_DIR"), "/llre_parser.rs"));
+    }
+
+    use llre::{NonTerminal as N, Terminal as T, Tree};
+
+    fn tokens(regex: &str) -> Vec<(T, String)> {
+        silly_lex::Lexer::new(regex)
+            .iter()
+            .map(|token| {
+                let kind = T::from_name(&token.kind.to_string()).unwrap();
+                (kind, token.data)
+            })
+            .collect()
+    }
+
+    /// The leaves of the tree, in order.
+    fn leaves(tree: &Tree<String>) -> Vec<&str> {
+        match tree {
+            Tree::Leaf(data) => vec![data.as_str()],
+            Tree::Node { children, .. } => children.iter().flat_map(leaves).collect(),
+        }
+    }
+
+    #[test]
+    fn generated_regex_parser() {
+        let tree = llre::parse(tokens("a(b|c-d)*")).unwrap();
+
+        match &tree {
+            Tree::Node {
+                non_terminal,
+                production,
+                ..
+            } => assert_eq!((*non_terminal, *production), (N::Re, 0)),
+            _ => panic!("Expected a node"),
+        }
+
+        assert_eq!(
+            leaves(&tree),
+            vec!["a", "(", "b", "|", "c", "-", "d", ")", "*"]
+        );
+    }
+
+    #[test]
+    fn generated_syntax_errors() {
+        let error = llre::parse(tokens("a(b")).unwrap_err();
+        assert_eq!(error.position, 3);
+        assert_eq!(error.found, T::End);
+        assert!(error.expected.contains(&T::Close));
+
+        let error = llre::parse(tokens("a)")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Syntax error at token 1: expected one of $, found close"
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        let cfg: CFG = "RE_1 -> '+' re_1 $ | END end\nEND -> lambda"
+            .parse()
+            .unwrap();
+        let names = Names::new(&cfg);
+        let terminal = |t: &str| names.terminals[&Terminal::new(t)].as_str();
+
+        assert_eq!(terminal("$"), "End");
+        assert_eq!(terminal("+"), "T1");
+        assert_eq!(terminal("end"), "End1");
+        assert_eq!(terminal("re_1"), "Re1");
+        assert_eq!(
+            names.non_terminals[&NonTerminal::new("RE_1")],
+            (String::from("Re1"), String::from("parse_re1"))
+        );
+    }
+}
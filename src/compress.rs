@@ -0,0 +1,372 @@
+use crate::classes::CharClasses;
+use crate::dfa::Dfa;
+use crate::error::Error;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A DFA transition table packed into the `default`, `base`, `next` and
+/// `check` arrays of lex. The entries a state stores start at `base[state]`
+/// in `next`, each marked by the state in `check`; every other entry is the
+/// one of `default[state]`, or an error without a default. Rows can then
+/// share their slots and any entries they have in common.
+///
+/// ```text
+/// 3 2
+/// accepting - - +
+/// default E 0 E
+/// base 0 1 2
+/// next 1 E 2 E
+/// check 0 E 1 E
+/// ```
+///
+/// The first line is the number of states and columns, and `E` marks an
+/// error or an unused slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressedTable {
+    pub columns: usize,
+    pub accepting: Vec<bool>,
+    pub default: Vec<Option<usize>>,
+    pub base: Vec<usize>,
+    pub next: Vec<Option<usize>>,
+    pub check: Vec<Option<usize>>,
+}
+
+impl CompressedTable {
+    /// Packs `rows`, which all have `columns` entries. Each row defaults to
+    /// the earlier row it differs least from, when that stores fewer entries
+    /// than none, and is placed at the first base where its entries fit.
+    pub fn new(rows: &[Vec<Option<usize>>], columns: usize, accepting: Vec<bool>) -> Self {
+        let mut table = Self {
+            columns,
+            accepting,
+            ..Self::default()
+        };
+
+        for (state, row) in rows.iter().enumerate() {
+            let stored = |default: Option<usize>| -> Vec<usize> {
+                (0..columns)
+                    .filter(|&c| row[c] != default.and_then(|d| rows[d][c]))
+                    .collect()
+            };
+
+            let (mut default, mut entries) = (None, stored(None));
+
+            for candidate in 0..state {
+                let candidate_entries = stored(Some(candidate));
+
+                if candidate_entries.len() < entries.len() {
+                    default = Some(candidate);
+                    entries = candidate_entries;
+                }
+            }
+
+            let fits = |base: usize| {
+                entries
+                    .iter()
+                    .all(|&c| table.check.get(base + c).copied().flatten().is_none())
+            };
+            let base = (0..).find(|&base| fits(base)).unwrap();
+
+            for &c in &entries {
+                if table.next.len() <= base + c {
+                    table.next.resize(base + c + 1, None);
+                    table.check.resize(base + c + 1, None);
+                }
+
+                table.next[base + c] = row[c];
+                table.check[base + c] = Some(state);
+            }
+
+            table.default.push(default);
+            table.base.push(base);
+        }
+
+        table
+    }
+
+    /// Packs the table of `dfa` with a column per class of `classes`.
+    pub fn from_dfa(dfa: &Dfa, classes: &CharClasses) -> Self {
+        let accepting = dfa.states.iter().map(|state| state.accepting).collect();
+        Self::new(&dfa.table(classes), classes.len(), accepting)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let input = read_to_string(path)
+            .with_context(|| format!("Could not read compressed table {}", path.display()))?;
+
+        input
+            .parse()
+            .with_context(|| format!("Could not parse compressed table {}", path.display()))
+    }
+
+    pub fn states(&self) -> usize {
+        self.base.len()
+    }
+
+    /// The state after `state` on `column`, `None` for an error.
+    pub fn next_state(&self, mut state: usize, column: usize) -> Option<usize> {
+        loop {
+            let slot = self.base[state] + column;
+
+            if self.check.get(slot).copied().flatten() == Some(state) {
+                return self.next[slot];
+            }
+
+            state = self.default[state]?;
+        }
+    }
+
+    /// Unpacks the rows the table was built from.
+    pub fn decode(&self) -> Vec<Vec<Option<usize>>> {
+        (0..self.states())
+            .map(|state| {
+                (0..self.columns)
+                    .map(|column| self.next_state(state, column))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for CompressedTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter<'_>, name: &str, entries: &[Option<usize>]| {
+            write!(f, "{}", name)?;
+
+            for entry in entries {
+                match entry {
+                    Some(entry) => write!(f, " {}", entry)?,
+                    None => write!(f, " E")?,
+                }
+            }
+
+            writeln!(f)
+        };
+
+        writeln!(f, "{} {}", self.states(), self.columns)?;
+        write!(f, "accepting")?;
+
+        for &accepting in &self.accepting {
+            write!(f, " {}", if accepting { '+' } else { '-' })?;
+        }
+
+        writeln!(f)?;
+        line(f, "default", &self.default)?;
+        let base: Vec<Option<usize>> = self.base.iter().copied().map(Some).collect();
+        line(f, "base", &base)?;
+        line(f, "next", &self.next)?;
+        line(f, "check", &self.check)
+    }
+}
+
+impl FromStr for CompressedTable {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+
+        let mut next_line = |name: &str| -> Result<(usize, Vec<String>)> {
+            let (i, line) = lines.next().ok_or_else(|| Error::TableParseError {
+                line: input.lines().count(),
+                message: format!("Expected the {} line", name),
+            })?;
+            let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+
+            if !name.is_empty() {
+                if words[0] != name {
+                    return Err(Error::TableParseError {
+                        line: i + 1,
+                        message: format!("Expected the {} line, found {:?}", name, words[0]),
+                    }
+                    .into());
+                }
+
+                words.remove(0);
+            }
+
+            Ok((i + 1, words))
+        };
+
+        let entry = |line: usize, word: &str| -> Result<Option<usize>> {
+            match word {
+                "E" => Ok(None),
+                word => word.parse().map(Some).map_err(|_| {
+                    Error::TableParseError {
+                        line,
+                        message: format!("Expected a state or E, found {:?}", word),
+                    }
+                    .into()
+                }),
+            }
+        };
+        let entries = |(line, words): (usize, Vec<String>)| -> Result<Vec<Option<usize>>> {
+            words.iter().map(|word| entry(line, word)).collect()
+        };
+
+        let (line, sizes) = next_line("")?;
+        let sizes = entries((line, sizes))?;
+        let (states, columns) = match sizes[..] {
+            [Some(states), Some(columns)] => (states, columns),
+            _ => {
+                return Err(Error::TableParseError {
+                    line,
+                    message: String::from("Expected the number of states and columns"),
+                }
+                .into())
+            }
+        };
+
+        let (line, words) = next_line("accepting")?;
+        let accepting = words
+            .iter()
+            .map(|word| match word.as_str() {
+                "+" => Ok(true),
+                "-" => Ok(false),
+                word => Err(Error::TableParseError {
+                    line,
+                    message: format!("Expected + or -, found {:?}", word),
+                }),
+            })
+            .collect::<Result<Vec<bool>, Error>>()?;
+
+        let default = entries(next_line("default")?)?;
+        let (line, words) = next_line("base")?;
+        let base = entries((line, words))?
+            .into_iter()
+            .map(|base| {
+                base.ok_or(Error::TableParseError {
+                    line,
+                    message: String::from("A base cannot be E"),
+                })
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        let next = entries(next_line("next")?)?;
+        let (line, words) = next_line("check")?;
+        let check = entries((line, words))?;
+
+        let table = Self {
+            columns,
+            accepting,
+            default,
+            base,
+            next,
+            check,
+        };
+
+        let consistent = [table.accepting.len(), table.default.len(), table.base.len()]
+            .iter()
+            .all(|&len| len == states)
+            && table.next.len() == table.check.len()
+            && table.next.iter().flatten().all(|&s| s < states)
+            && table.check.iter().flatten().all(|&s| s < states)
+            // Defaulting to an earlier state keeps lookups from cycling.
+            && (table.default.iter().enumerate()).all(|(s, d)| d.is_none_or(|d| d < s));
+
+        if !consistent {
+            return Err(Error::TableParseError {
+                line,
+                message: String::from("The arrays do not fit together"),
+            }
+            .into());
+        }
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::Regex;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn compressed() {
+        let e = None;
+        let rows = vec![
+            vec![Some(1), Some(2), e, e, e, e],
+            vec![Some(1), Some(2), e, e, e, Some(3)],
+            vec![Some(1), Some(2), e, e, e, Some(3)],
+            vec![e, e, e, Some(0), e, e],
+            vec![e, e, e, e, e, e],
+        ];
+        let accepting = vec![false, false, true, true, false];
+        let table = CompressedTable::new(&rows, 6, accepting.clone());
+
+        assert_eq!(table.decode(), rows);
+        assert_eq!(table.default, vec![None, Some(0), Some(1), None, None]);
+        assert_eq!(table.base, vec![0, 0, 0, 0, 0]);
+        assert_eq!(table.next, vec![Some(1), Some(2), e, Some(0), e, Some(3)]);
+        assert_eq!(table.accepting, accepting);
+
+        // A row that turns an entry of its default into an error stores it.
+        let rows = vec![vec![Some(0), Some(0), Some(0)], vec![Some(0), Some(0), e]];
+        let table = CompressedTable::new(&rows, 3, vec![false, true]);
+        assert_eq!(table.default, vec![None, Some(0)]);
+        assert_eq!(table.decode(), rows);
+    }
+
+    #[test]
+    fn round_trip() {
+        let alphabet: BTreeSet<char> = "abcdef".chars().collect();
+        let regex = Regex::Concat(vec![
+            Regex::star(Regex::Any),
+            Regex::Char('a'),
+            Regex::Any,
+            Regex::Any,
+        ]);
+        let dfa = Dfa::from_regex(&regex, &alphabet);
+        let classes = CharClasses::new(&alphabet, std::slice::from_ref(&dfa));
+        let table = CompressedTable::from_dfa(&dfa, &classes);
+
+        assert_eq!(table.decode(), dfa.table(&classes));
+        assert!(table.next.len() < dfa.states.len() * classes.len());
+        assert_eq!(table.to_string().parse::<CompressedTable>().unwrap(), table);
+
+        let written =
+            "3 2\naccepting - - +\ndefault E 0 E\nbase 0 1 2\nnext 1 E 2 E\ncheck 0 E 1 E\n";
+        let table: CompressedTable = written.parse().unwrap();
+        assert_eq!(table.to_string(), written);
+        assert_eq!(
+            table.decode(),
+            vec![
+                vec![Some(1), None],
+                vec![Some(1), Some(2)],
+                vec![None, None]
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let error = |input: &str| input.parse::<CompressedTable>().unwrap_err().to_string();
+
+        assert_eq!(
+            error("3 2\naccepting - x +"),
+            "line 2: Expected + or -, found \"x\""
+        );
+        assert_eq!(
+            error("1 1\naccepting -\ndefault E\nbase E"),
+            "line 4: A base cannot be E"
+        );
+        assert_eq!(
+            error("1 1\naccepting -\ndefault E\nbase 0\nnext 0 0\ncheck 0"),
+            "line 6: The arrays do not fit together"
+        );
+        assert_eq!(
+            error("1 1\naccepting -\nbase 0"),
+            "line 3: Expected the default line, found \"base\""
+        );
+        assert_eq!(
+            error("1 1\naccepting -\ndefault 0\nbase 0\nnext\ncheck"),
+            "line 6: The arrays do not fit together"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cfg::CFG;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use std::collections::BTreeMap;
+
+/// A CYK recognizer, independent of the LL machinery. The grammar is
+/// converted to Chomsky normal form when the recognizer is created.
+#[derive(Debug, Clone)]
+pub struct CYK {
+    cnf: CFG,
+    non_terminals: Vec<NonTerminal>,
+    terminal_rules: BTreeMap<Terminal, Vec<usize>>,
+    binary_rules: Vec<(usize, usize, usize)>,
+    start: usize,
+    accepts_lambda: bool,
+}
+
+impl CYK {
+    pub fn new(cfg: &CFG) -> Self {
+        let cnf = cfg.to_cnf();
+        let non_terminals: Vec<NonTerminal> = cnf.non_terminals.iter().cloned().collect();
+        let index_of = |nt: &NonTerminal| non_terminals.binary_search(nt).unwrap();
+
+        let mut terminal_rules: BTreeMap<Terminal, Vec<usize>> = BTreeMap::new();
+        let mut binary_rules = Vec::new();
+        let mut accepts_lambda = false;
+
+        for (nt, production_indices) in &cnf.production_map {
+            for idx in production_indices {
+                match cnf.productions[*idx].symbols() {
+                    [Symbol::Terminal(t)] => terminal_rules
+                        .entry(t.clone())
+                        .or_default()
+                        .push(index_of(nt)),
+                    [Symbol::NonTerminal(b), Symbol::NonTerminal(c)] => {
+                        binary_rules.push((index_of(nt), index_of(b), index_of(c)))
+                    }
+                    [Symbol::Lambda] => accepts_lambda = true,
+                    other => unreachable!("Production is not in CNF: {:?}", other),
+                }
+            }
+        }
+
+        let start = index_of(&cnf.start_symbol);
+
+        Self {
+            cnf,
+            non_terminals,
+            terminal_rules,
+            binary_rules,
+            start,
+            accepts_lambda,
+        }
+    }
+
+    /// The Chomsky normal form grammar the recognizer runs on.
+    pub fn cnf(&self) -> &CFG {
+        &self.cnf
+    }
+
+    /// Whether the grammar derives exactly `input`. Grammars that end in `$`
+    /// expect the end marker to be part of the input.
+    pub fn accepts(&self, input: &[Terminal]) -> bool {
+        let n = input.len();
+
+        if n == 0 {
+            return self.accepts_lambda;
+        }
+
+        let width = self.non_terminals.len();
+
+        // table[len - 1][i][A] is true when A derives input[i..i + len]
+        let mut table = vec![vec![vec![false; width]; n]; n];
+
+        for (i, terminal) in input.iter().enumerate() {
+            for nt in self.terminal_rules.get(terminal).into_iter().flatten() {
+                table[0][i][*nt] = true;
+            }
+        }
+
+        for len in 2..=n {
+            for i in 0..=n - len {
+                for split in 1..len {
+                    for &(a, b, c) in &self.binary_rules {
+                        if table[split - 1][i][b] && table[len - split - 1][i + split][c] {
+                            table[len - 1][i][a] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        table[n - 1][0][self.start]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use silly_lex::Lexer;
+    use std::iter::once;
+
+    fn tokens(regex: &str) -> Vec<Terminal> {
+        Lexer::new(regex)
+            .iter()
+            .map(|t| Terminal::new(t.kind.to_string()))
+            .chain(once(Terminal::new("$")))
+            .collect()
+    }
+
+    #[test]
+    fn regex_membership() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+        let cyk = CYK::new(&cfg);
+
+        for regex in &[
+            "",
+            "a",
+            "ab*",
+            "(a|b)+c",
+            "a-z.",
+            "(|a)",
+            "Ab(cd-e+)*(.|012)3",
+        ] {
+            assert!(
+                cyk.accepts(&tokens(regex)),
+                "{:?} should be accepted",
+                regex
+            );
+        }
+
+        for regex in &["*", "(a", "a)", "a**", "a-", "-a", "a|*"] {
+            assert!(
+                !cyk.accepts(&tokens(regex)),
+                "{:?} should be rejected",
+                regex
+            );
+        }
+
+        assert!(!cyk.accepts(&[]));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::char_set::{next_char, prev_char, CharSet};
+use crate::nfa_generator::NFAGenerator;
+use crate::regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A deterministic automaton whose edges are labelled with character sets,
+/// built from an NFA by subset construction. State 0 is the start state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    pub states: Vec<DfaState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DfaState {
+    pub accepting: bool,
+    /// Disjoint labels, sorted by their first character.
+    pub transitions: Vec<(CharSet, usize)>,
+}
+
+impl Dfa {
+    /// Determinizes the NFA in `nfa` that runs from `start` to `accept`.
+    pub fn from_nfa(nfa: &NFAGenerator, start: usize, accept: usize) -> Self {
+        let mut lambdas: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+        for (&(from, to), &is_lambda) in &nfa.lambda_transitions {
+            if is_lambda {
+                lambdas.entry(from).or_default().push(to);
+            }
+        }
+
+        let closure = |states: BTreeSet<usize>| {
+            let mut closed = states.clone();
+            let mut stack: Vec<usize> = states.into_iter().collect();
+
+            while let Some(state) = stack.pop() {
+                for &next in lambdas.get(&state).into_iter().flatten() {
+                    if closed.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+
+            closed
+        };
+
+        let mut dfa = Dfa { states: vec![] };
+        let mut ids: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
+        let mut queue: VecDeque<BTreeSet<usize>> = VecDeque::new();
+
+        let first = closure(std::iter::once(start).collect());
+        ids.insert(first.clone(), 0);
+        dfa.states.push(DfaState::default());
+        queue.push_back(first);
+
+        while let Some(set) = queue.pop_front() {
+            let id = ids[&set];
+            dfa.states[id].accepting = set.contains(&accept);
+
+            let edges: Vec<(&CharSet, usize)> = nfa
+                .transitions
+                .iter()
+                .filter(|((from, _), _)| set.contains(from))
+                .map(|(&(_, to), label)| (label, to))
+                .collect();
+
+            for (targets, label) in split(&edges) {
+                let target = closure(targets);
+
+                let next = match ids.get(&target) {
+                    Some(&next) => next,
+                    None => {
+                        let next = dfa.states.len();
+                        ids.insert(target.clone(), next);
+                        dfa.states.push(DfaState::default());
+                        queue.push_back(target);
+                        next
+                    }
+                };
+
+                dfa.states[id].transitions.push((label, next));
+            }
+
+            dfa.states[id]
+                .transitions
+                .sort_by_key(|(label, _)| label.ranges()[0]);
+        }
+
+        dfa
+    }
+
+    /// Builds the DFA of `regex`, where `.` means any character of `alphabet`.
+    pub fn from_regex(regex: &Regex, alphabet: &BTreeSet<char>) -> Self {
+        let mut nfa = NFAGenerator::new(alphabet.clone(), None);
+        nfa.add_to_table(regex, 0, 1);
+        Self::from_nfa(&nfa, 0, 1)
+    }
+
+    pub fn next(&self, state: usize, c: char) -> Option<usize> {
+        self.states[state]
+            .transitions
+            .iter()
+            .find(|(label, _)| label.contains(c))
+            .map(|(_, next)| *next)
+    }
+
+    pub fn accepts(&self, input: &str) -> bool {
+        self.run(input.chars())
+    }
+
+    /// Runs a DFA over the symbols of `utf8::byte_alphabet` on raw bytes.
+    pub fn accepts_bytes(&self, input: &[u8]) -> bool {
+        self.run(input.iter().map(|&b| char::from(b)))
+    }
+
+    fn run(&self, input: impl Iterator<Item = char>) -> bool {
+        let mut state = 0;
+
+        for c in input {
+            state = match self.next(state, c) {
+                Some(next) => next,
+                None => return false,
+            };
+        }
+
+        self.states[state].accepting
+    }
+}
+
+/// Splits the labels of `edges` into disjoint sets of characters that all
+/// lead to the same NFA states.
+fn split(edges: &[(&CharSet, usize)]) -> BTreeMap<BTreeSet<usize>, CharSet> {
+    let mut bounds: BTreeSet<char> = BTreeSet::new();
+
+    for (label, _) in edges {
+        for &(start, end) in label.ranges() {
+            bounds.insert(start);
+            bounds.extend(next_char(end));
+        }
+    }
+
+    let bounds: Vec<char> = bounds.into_iter().collect();
+    let mut targets: BTreeMap<BTreeSet<usize>, CharSet> = BTreeMap::new();
+
+    for (i, &start) in bounds.iter().enumerate() {
+        // Every label either contains all of [start, end] or none of it.
+        let end = bounds
+            .get(i + 1)
+            .and_then(|&next| prev_char(next))
+            .unwrap_or(char::MAX);
+
+        let to: BTreeSet<usize> = edges
+            .iter()
+            .filter(|(label, _)| label.contains(start))
+            .map(|(_, to)| *to)
+            .collect();
+
+        if !to.is_empty() {
+            let chars = targets.entry(to).or_default();
+            *chars = chars.union(&CharSet::range(start, end));
+        }
+    }
+
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dfa(regex: &str) -> Dfa {
+        Dfa::from_regex(&regex.parse().unwrap(), &"abcd".chars().collect())
+    }
+
+    #[test]
+    fn matching() {
+        let abb = dfa("(a|b)*abb");
+        assert!(abb.accepts("abb") && abb.accepts("babaabb"));
+        assert!(!abb.accepts("") && !abb.accepts("abba") && !abb.accepts("abc"));
+
+        let dots = dfa("a.+|");
+        assert!(dots.accepts("") && dots.accepts("ab") && dots.accepts("addd"));
+        assert!(!dots.accepts("a") && !dots.accepts("ae"));
+
+        let range = dfa("a-c*d");
+        assert!(range.accepts("d") && range.accepts("abcbad"));
+        assert!(!range.accepts("abdd"));
+
+        let greek = dfa("α-ω+\\x{1F600}|x");
+        assert!(greek.accepts("λμ😀") && greek.accepts("x"));
+        assert!(!greek.accepts("😀") && !greek.accepts("a😀"));
+    }
+
+    #[test]
+    fn interval_edges() {
+        // A range costs one edge no matter how many characters it holds.
+        let wide = Regex::Concat(vec![
+            Regex::Set(CharSet::range('\u{100}', '\u{10FFFF}')),
+            Regex::Char('a'),
+        ]);
+        let dfa = Dfa::from_regex(&wide, &BTreeSet::new());
+
+        assert_eq!(dfa.states.len(), 3);
+        assert_eq!(dfa.states[0].transitions.len(), 1);
+        assert!(dfa.accepts("\u{1F600}a") && !dfa.accepts("aa"));
+
+        // Overlapping labels are split into disjoint ones.
+        let overlapping = Regex::Alt(vec![
+            Regex::Concat(vec![Regex::Set(CharSet::range('a', 'm')), Regex::Char('x')]),
+            Regex::Concat(vec![Regex::Set(CharSet::range('g', 'z')), Regex::Char('y')]),
+        ]);
+        let dfa = Dfa::from_regex(&overlapping, &BTreeSet::new());
+        let labels: Vec<&[(char, char)]> = dfa.states[0]
+            .transitions
+            .iter()
+            .map(|(label, _)| label.ranges())
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec![&[('a', 'f')][..], &[('g', 'm')][..], &[('n', 'z')][..]]
+        );
+        assert!(dfa.accepts("hx") && dfa.accepts("hy") && !dfa.accepts("ay"));
+    }
+}
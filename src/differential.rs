@@ -0,0 +1,254 @@
+use crate::char_set::CharSet;
+use crate::dfa::Dfa;
+use crate::examples::{Examples, Rng};
+use crate::nfa_generator::NFAGenerator;
+use crate::regex::Regex;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A string the automata of WRECK and the `regex` crate disagree on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    pub input: String,
+    /// `NFA` or `DFA`.
+    pub automaton: &'static str,
+    /// Whether WRECK's automaton accepts `input`, the `regex` crate does the
+    /// opposite.
+    pub accepted: bool,
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (wreck, crate_regex) = match self.accepted {
+            true => ("accepts", "rejects"),
+            false => ("rejects", "accepts"),
+        };
+
+        write!(
+            f,
+            "the {} {} {:?} but the regex crate {} it",
+            self.automaton, wreck, self.input, crate_regex
+        )
+    }
+}
+
+/// Writes `regex` as a pattern for the `regex` crate that matches exactly the
+/// same strings, anchored with `^...$`. `.` is the characters of `alphabet`.
+pub fn to_pattern(regex: &Regex, alphabet: &BTreeSet<char>) -> String {
+    format!("^(?:{})$", pattern(regex, alphabet))
+}
+
+fn pattern(regex: &Regex, alphabet: &BTreeSet<char>) -> String {
+    let group = |regex: &Regex| format!("(?:{})", pattern(regex, alphabet));
+
+    match regex {
+        Regex::Empty => String::new(),
+        Regex::Char(c) => ::regex::escape(&c.to_string()),
+        Regex::Set(chars) => class(chars),
+        Regex::Any => class(&alphabet.iter().collect()),
+        Regex::Concat(regexes) => regexes.iter().map(group).collect(),
+        Regex::Alt(regexes) if regexes.is_empty() => class(&CharSet::new()),
+        Regex::Alt(regexes) => {
+            let alternatives: Vec<String> = regexes.iter().map(group).collect();
+            alternatives.join("|")
+        }
+        Regex::Star(regex) => format!("{}*", group(regex)),
+        Regex::Plus(regex) => format!("{}+", group(regex)),
+        Regex::Optional(regex) => format!("{}?", group(regex)),
+        Regex::Repeat { regex, min, max } => match max {
+            Some(max) => format!("{}{{{},{}}}", group(regex), min, max),
+            None => format!("{}{{{},}}", group(regex), min),
+        },
+    }
+}
+
+/// A character class of `chars`, or one that matches nothing when empty.
+fn class(chars: &CharSet) -> String {
+    if chars.is_empty() {
+        return String::from(r"[^\x{0}-\x{10FFFF}]");
+    }
+
+    let ranges: String = chars
+        .ranges()
+        .iter()
+        .map(|&(start, end)| format!(r"\x{{{:X}}}-\x{{{:X}}}", start as u32, end as u32))
+        .collect();
+
+    format!("[{}]", ranges)
+}
+
+/// Compares the NFA and DFA WRECK builds for `regex` against the `regex`
+/// crate on `count` random strings over `alphabet` of up to `max_length`
+/// characters, plus as many strings the DFA accepts and ones just outside.
+/// The first disagreement, shortest inputs first, is shrunk to a minimal one.
+pub fn verify(
+    regex: &Regex,
+    alphabet: &BTreeSet<char>,
+    count: usize,
+    max_length: usize,
+    rng: &mut Rng,
+) -> Result<Option<Disagreement>> {
+    let pattern = to_pattern(regex, alphabet);
+    verify_against(regex, &pattern, alphabet, count, max_length, rng)
+}
+
+/// Like `verify`, but against any `pattern` of the `regex` crate.
+pub fn verify_against(
+    regex: &Regex,
+    pattern: &str,
+    alphabet: &BTreeSet<char>,
+    count: usize,
+    max_length: usize,
+    rng: &mut Rng,
+) -> Result<Option<Disagreement>> {
+    let expected = ::regex::Regex::new(pattern)
+        .with_context(|| format!("The regex crate rejected the pattern {}", pattern))?;
+
+    let mut nfa = NFAGenerator::new(alphabet.clone(), None);
+    nfa.add_to_table(regex, 0, 1);
+    let dfa = Dfa::from_regex(regex, alphabet);
+
+    let disagreement = |input: &str| {
+        let matched = expected.is_match(input);
+
+        if nfa.accepts(0, 1, input) != matched {
+            Some(("NFA", !matched))
+        } else if dfa.accepts(input) != matched {
+            Some(("DFA", !matched))
+        } else {
+            None
+        }
+    };
+
+    let symbols: Vec<char> = alphabet.iter().copied().collect();
+    let mut examples = Examples::new(regex, alphabet);
+    let accepted = examples.random(count, max_length, rng);
+    let mut inputs: BTreeSet<(usize, String)> = BTreeSet::new();
+
+    for input in examples
+        .near_misses(&accepted, count)
+        .into_iter()
+        .chain(accepted)
+    {
+        inputs.insert((input.chars().count(), input));
+    }
+
+    for _ in 0..count {
+        let length = match symbols.is_empty() {
+            true => 0,
+            false => rng.below(max_length + 1),
+        };
+        let input: String = (0..length)
+            .map(|_| symbols[rng.below(symbols.len())])
+            .collect();
+        inputs.insert((length, input));
+    }
+
+    let mut input = match inputs
+        .into_iter()
+        .find(|(_, input)| disagreement(input).is_some())
+    {
+        Some((_, input)) => input,
+        None => return Ok(None),
+    };
+
+    // Drop characters, or swap them for earlier ones, while it still fails.
+    'shrink: loop {
+        let chars: Vec<char> = input.chars().collect();
+
+        for i in 0..chars.len() {
+            let mut smaller: Vec<Vec<char>> = vec![[&chars[..i], &chars[i + 1..]].concat()];
+
+            for &c in symbols.iter().take_while(|&&c| c < chars[i]) {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                smaller.push(replaced);
+            }
+
+            for candidate in smaller {
+                let candidate: String = candidate.into_iter().collect();
+
+                if disagreement(&candidate).is_some() {
+                    input = candidate;
+                    continue 'shrink;
+                }
+            }
+        }
+
+        break;
+    }
+
+    let (automaton, accepted) = disagreement(&input).unwrap();
+
+    Ok(Some(Disagreement {
+        input,
+        automaton,
+        accepted,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::optimize;
+
+    #[test]
+    fn patterns() {
+        let alphabet: BTreeSet<char> = "ab.".chars().collect();
+        let pattern = |regex: &str| to_pattern(&regex.parse().unwrap(), &alphabet);
+
+        assert_eq!(pattern("ab"), "^(?:(?:a)(?:b))$");
+        assert_eq!(pattern("a|"), "^(?:(?:a)|(?:))$");
+        assert_eq!(pattern("\\.*"), r"^(?:(?:\.)*)$");
+        assert_eq!(pattern("."), r"^(?:[\x{2E}-\x{2E}\x{61}-\x{62}])$");
+        assert_eq!(pattern("(a|b)*"), r"^(?:(?:(?:a)|(?:b))*)$");
+        assert_eq!(pattern("z-a"), r"^(?:[^\x{0}-\x{10FFFF}])$");
+    }
+
+    #[test]
+    fn agrees() {
+        let alphabet: BTreeSet<char> = "abc \\".chars().collect();
+
+        for regex in &[
+            "(\\s|\\\\|b|c)*a",
+            "(a|b)*abb",
+            "a+b*|c",
+            "((a|)b)*",
+            ".c.|a-b+",
+            "(ab)+|(ab)*c",
+            "",
+        ] {
+            let regex: Regex = regex.parse().unwrap();
+
+            for regex in &[regex.clone(), optimize(regex)] {
+                let found = verify(regex, &alphabet, 300, 8, &mut Rng::new(3)).unwrap();
+                assert_eq!(found, None, "{}", regex);
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_counterexample() {
+        // As if the loop back of `(ab)*` had gone missing.
+        let alphabet: BTreeSet<char> = "abc".chars().collect();
+        let regex: Regex = "c(ab)*".parse().unwrap();
+        let broken = to_pattern(&"c(ab|)".parse().unwrap(), &alphabet);
+
+        assert_eq!(
+            verify_against(&regex, &broken, &alphabet, 100, 8, &mut Rng::new(3)).unwrap(),
+            Some(Disagreement {
+                input: String::from("cabab"),
+                automaton: "NFA",
+                accepted: true,
+            })
+        );
+        assert_eq!(
+            verify_against(&regex, "^c$", &alphabet, 100, 8, &mut Rng::new(3))
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "the NFA accepts \"cab\" but the regex crate rejects it"
+        );
+    }
+}
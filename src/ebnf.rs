@@ -0,0 +1,178 @@
+use crate::cfg::CFG;
+use crate::symbol::{NonTerminal, Symbol};
+use std::collections::VecDeque;
+
+/// One item on the right hand side of an EBNF rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Symbol(Symbol),
+    /// `( a b | c )`
+    Group(Vec<Vec<Expr>>),
+    /// `e*`
+    Star(Box<Expr>),
+    /// `e+`
+    Plus(Box<Expr>),
+    /// `e?` or `[ e ]`
+    Optional(Box<Expr>),
+}
+
+impl CFG {
+    /// Adds `lhs -> alternatives`, desugaring EBNF operators into fresh helper
+    /// non-terminals named after `lhs`:
+    ///
+    /// - `e*` becomes `N` with `N -> e N | lambda`
+    /// - `e+` becomes `e N` with `N -> e N | lambda`
+    /// - `e?` and `[ e ]` become `N` with `N -> e | lambda`
+    /// - `( a | b )` becomes `N` with `N -> a | b`
+    ///
+    /// Returns the indices of the productions of `lhs`, one per alternative.
+    pub fn add_rule(&mut self, lhs: &NonTerminal, alternatives: &[Vec<Expr>]) -> Vec<usize> {
+        let first = self.productions.len();
+        let mut queue = VecDeque::new();
+        queue.push_back((lhs.clone(), alternatives.to_vec()));
+
+        while let Some((nt, alternatives)) = queue.pop_front() {
+            for alternative in &alternatives {
+                let mut symbols = Vec::new();
+
+                for expr in alternative {
+                    symbols.append(&mut self.lower(lhs, expr, &mut queue));
+                }
+
+                symbols.retain(|s| !s.is_lambda());
+                if symbols.is_empty() {
+                    symbols.push(Symbol::Lambda);
+                }
+
+                self.add_production(nt.clone(), symbols);
+            }
+        }
+
+        (first..first + alternatives.len()).collect()
+    }
+
+    /// Lowers `expr` to plain symbols, queueing the rules of any helper
+    /// non-terminal it needs.
+    fn lower(
+        &mut self,
+        base: &NonTerminal,
+        expr: &Expr,
+        queue: &mut VecDeque<(NonTerminal, Vec<Vec<Expr>>)>,
+    ) -> Vec<Symbol> {
+        let lambda = || vec![Expr::Symbol(Symbol::Lambda)];
+
+        match expr {
+            Expr::Symbol(symbol) => vec![symbol.clone()],
+            Expr::Group(alternatives) if alternatives.len() == 1 => {
+                let mut symbols = Vec::new();
+                for expr in &alternatives[0] {
+                    symbols.append(&mut self.lower(base, expr, queue));
+                }
+                symbols
+            }
+            Expr::Group(alternatives) => {
+                let nt = self.helper(base, alternatives.clone(), queue);
+                vec![Symbol::from_non_terminal(nt)]
+            }
+            Expr::Star(inner) | Expr::Plus(inner) => {
+                let symbols = self.lower(base, inner, queue);
+                let nt = self.helper(base, Vec::new(), queue);
+
+                // The helper refers to itself, so fill in its rule once it has a name.
+                let mut repeat: Vec<Expr> = symbols.iter().cloned().map(Expr::Symbol).collect();
+                repeat.push(Expr::Symbol(Symbol::from_non_terminal(nt.clone())));
+                queue.back_mut().unwrap().1 = vec![repeat, lambda()];
+
+                if let Expr::Plus(_) = expr {
+                    let mut symbols = symbols;
+                    symbols.push(Symbol::from_non_terminal(nt));
+                    symbols
+                } else {
+                    vec![Symbol::from_non_terminal(nt)]
+                }
+            }
+            Expr::Optional(inner) => {
+                let symbols = self.lower(base, inner, queue);
+                let present = symbols.into_iter().map(Expr::Symbol).collect();
+                let nt = self.helper(base, vec![present, lambda()], queue);
+                vec![Symbol::from_non_terminal(nt)]
+            }
+        }
+    }
+
+    fn helper(
+        &mut self,
+        base: &NonTerminal,
+        alternatives: Vec<Vec<Expr>>,
+        queue: &mut VecDeque<(NonTerminal, Vec<Vec<Expr>>)>,
+    ) -> NonTerminal {
+        let nt = self.fresh_non_terminal(base.non_terminal());
+        self.non_terminals.insert(nt.clone());
+        queue.push_back((nt.clone(), alternatives));
+        nt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cyk::CYK;
+    use crate::symbol::Terminal;
+
+    fn productions(cfg: &CFG, nt: &str) -> Vec<String> {
+        cfg.production_map[&NonTerminal::new(nt)]
+            .iter()
+            .map(|idx| cfg.productions[*idx].to_string().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn desugar_helpers() {
+        let cfg: CFG = "LIST -> item (comma item)* [dot]\n\
+                        DIGITS -> digit+"
+            .parse()
+            .unwrap();
+
+        assert_eq!(productions(&cfg, "LIST"), vec!["item LIST_1 LIST_2"]);
+        assert_eq!(
+            productions(&cfg, "LIST_1"),
+            vec!["comma item LIST_1", "lambda"]
+        );
+        assert_eq!(productions(&cfg, "LIST_2"), vec!["dot", "lambda"]);
+        assert_eq!(productions(&cfg, "DIGITS"), vec!["digit DIGITS_1"]);
+        assert_eq!(
+            productions(&cfg, "DIGITS_1"),
+            vec!["digit DIGITS_1", "lambda"]
+        );
+    }
+
+    #[test]
+    fn ebnf_regex_grammar_matches_plain() {
+        let plain = CFG::from_file("llre.cfg").unwrap();
+
+        let ebnf: CFG = "RE -> ALT $\n\
+                         ALT -> SEQ (pipe SEQ)*\n\
+                         SEQ -> ATOM*\n\
+                         ATOM -> NUCLEUS (kleene | plus)?\n\
+                         NUCLEUS -> open ALT close | char [dash char] | dot"
+            .parse()
+            .unwrap();
+
+        let (plain, ebnf) = (CYK::new(&plain), CYK::new(&ebnf));
+
+        let inputs: &[&[&str]] = &[
+            &["$"],
+            &["char", "kleene", "$"],
+            &["open", "char", "pipe", "pipe", "close", "plus", "$"],
+            &["char", "dash", "char", "dot", "$"],
+            &["char", "dash", "$"],
+            &["kleene", "$"],
+            &["open", "char", "$"],
+        ];
+
+        for input in inputs {
+            let input: Vec<Terminal> = input.iter().map(|t| Terminal::new(*t)).collect();
+            assert_eq!(plain.accepts(&input), ebnf.accepts(&input), "{:?}", input);
+        }
+    }
+}
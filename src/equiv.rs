@@ -0,0 +1,112 @@
+use crate::classes::CharClasses;
+use crate::dfa::Dfa;
+use crate::regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A state of the product of two DFAs, `None` once one of them has no
+/// transition left.
+type Pair = (Option<usize>, Option<usize>);
+
+/// Runs `a` and `b` side by side over the characters of `alphabet` and finds
+/// the shortest string, the first in alphabetical order among those, that
+/// leaves them in states where `accept(a_accepts, b_accepts)` holds. Strings
+/// that neither DFA can continue are not searched further, so
+/// `accept(false, false)` only counts when one of them still can.
+pub fn product_witness(
+    a: &Dfa,
+    b: &Dfa,
+    alphabet: &BTreeSet<char>,
+    accept: impl Fn(bool, bool) -> bool,
+) -> Option<String> {
+    // Characters of a class lead every state to the same place, so the first
+    // one stands for all of them.
+    let classes = CharClasses::new(alphabet, &[a.clone(), b.clone()]);
+    let symbols: Vec<char> = classes.classes().iter().map(|c| c.ranges()[0].0).collect();
+
+    let accepting =
+        |dfa: &Dfa, state: Option<usize>| state.is_some_and(|s| dfa.states[s].accepting);
+    let step = |dfa: &Dfa, state: Option<usize>, c: char| state.and_then(|s| dfa.next(s, c));
+
+    let start = (Some(0), Some(0));
+    let mut parents: BTreeMap<Pair, Option<(Pair, char)>> = BTreeMap::new();
+    let mut queue: VecDeque<Pair> = VecDeque::new();
+
+    parents.insert(start, None);
+    queue.push_back(start);
+
+    while let Some(pair) = queue.pop_front() {
+        if accept(accepting(a, pair.0), accepting(b, pair.1)) {
+            let mut witness = Vec::new();
+            let mut current = pair;
+
+            while let Some((parent, c)) = parents[&current] {
+                witness.push(c);
+                current = parent;
+            }
+
+            return Some(witness.into_iter().rev().collect());
+        }
+
+        for &c in &symbols {
+            let next = (step(a, pair.0, c), step(b, pair.1, c));
+
+            // Both are stuck, nothing after this can be accepted.
+            if next == (None, None) || parents.contains_key(&next) {
+                continue;
+            }
+
+            parents.insert(next, Some((pair, c)));
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+/// The shortest string over `alphabet` that exactly one of `a` and `b`
+/// matches, or `None` when they match the same strings. `.` stands for any
+/// character of `alphabet`.
+pub fn distinguishing_string(a: &Regex, b: &Regex, alphabet: &BTreeSet<char>) -> Option<String> {
+    let (a, b) = (Dfa::from_regex(a, alphabet), Dfa::from_regex(b, alphabet));
+    product_witness(&a, &b, alphabet, |a, b| a != b)
+}
+
+/// Whether `a` and `b` match the same strings over `alphabet`.
+pub fn equivalent(a: &Regex, b: &Regex, alphabet: &BTreeSet<char>) -> bool {
+    distinguishing_string(a, b, alphabet).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn differ(a: &str, b: &str, alphabet: &str) -> Option<String> {
+        distinguishing_string(
+            &a.parse().unwrap(),
+            &b.parse().unwrap(),
+            &alphabet.chars().collect(),
+        )
+    }
+
+    #[test]
+    fn equivalence() {
+        assert_eq!(differ("(a|b)*", "(a*b*)*", "ab"), None);
+        assert_eq!(differ("a+", "aa*", "ab"), None);
+        assert_eq!(differ("a-c", "a|b|c", "abc"), None);
+        assert_eq!(differ(".", "a|b", "ab"), None);
+        // Characters outside the alphabet never appear.
+        assert_eq!(differ("a|z", "a", "ab"), None);
+    }
+
+    #[test]
+    fn shortest_witness() {
+        assert_eq!(differ("a*", "a+", "ab"), Some(String::new()));
+        assert_eq!(
+            differ("(a|b)*abb", "(a|b)*bb", "ab"),
+            Some(String::from("bb"))
+        );
+        assert_eq!(differ("ab|ba", "ab|bb", "ab"), Some(String::from("ba")));
+        assert_eq!(differ(".", "a", "ab"), Some(String::from("b")));
+        assert_eq!(differ("a*", "(aa)*", "a"), Some(String::from("a")));
+    }
+}
@@ -4,4 +4,16 @@ use thiserror::Error;
 pub enum Error {
     #[error("Invalid Symbol: {0:?}")]
     SymbolParseError(String),
+    #[error("{line}:{column}: {message}")]
+    GrammarParseError {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("line {line}: {message}")]
+    TableParseError { line: usize, message: String },
+    #[error("Invalid regex AST: {0}")]
+    AstError(String),
+    #[error("{0} matches the empty string, mark it !nullable if that is intended")]
+    UnmarkedNullable(String),
 }
@@ -0,0 +1,261 @@
+use crate::dfa::Dfa;
+use crate::regex::Regex;
+use std::collections::BTreeSet;
+
+/// Generates strings a regex accepts, and some it just barely rejects, over
+/// the characters of an alphabet.
+#[derive(Debug, Clone)]
+pub struct Examples {
+    dfa: Dfa,
+    alphabet: Vec<char>,
+    /// `exact[k][state]` is whether `state` accepts after exactly `k` more
+    /// characters, filled in as far as a search has needed.
+    exact: Vec<Vec<bool>>,
+}
+
+impl Examples {
+    pub fn new(regex: &Regex, alphabet: &BTreeSet<char>) -> Self {
+        let dfa = Dfa::from_regex(regex, alphabet);
+        let exact = vec![dfa.states.iter().map(|state| state.accepting).collect()];
+
+        Self {
+            dfa,
+            alphabet: alphabet.iter().copied().collect(),
+            exact,
+        }
+    }
+
+    pub fn accepts(&self, input: &str) -> bool {
+        self.dfa.accepts(input)
+    }
+
+    /// The shortest accepted string, the first in alphabetical order among
+    /// those, or `None` when nothing is accepted.
+    pub fn shortest(&mut self) -> Option<String> {
+        self.first(1).pop()
+    }
+
+    /// The first `n` accepted strings in order of length, and alphabetically
+    /// within a length. Fewer when the regex accepts fewer.
+    pub fn first(&mut self, n: usize) -> Vec<String> {
+        let states = self.dfa.states.len();
+        let mut found = Vec::new();
+
+        // A finite language has no string longer than the DFA has states, and
+        // an infinite one has a string for every `n` somewhere further on.
+        let infinite = (states..2 * states).any(|k| self.accepts_after(0, k));
+        let mut length = 0;
+
+        while found.len() < n && (infinite || length < states) {
+            self.extend(0, length, &mut String::new(), n, &mut found);
+            length += 1;
+        }
+
+        found
+    }
+
+    /// `count` accepted strings of at most `max_length` characters, picked by
+    /// first choosing one of the lengths the regex accepts and then each
+    /// character among those that can still finish at that length.
+    pub fn random(&mut self, count: usize, max_length: usize, rng: &mut Rng) -> Vec<String> {
+        let lengths: Vec<usize> = (0..=max_length)
+            .filter(|&k| self.accepts_after(0, k))
+            .collect();
+
+        if lengths.is_empty() {
+            return vec![];
+        }
+
+        let exact = &self.exact;
+
+        (0..count)
+            .map(|_| {
+                let mut remaining = lengths[rng.below(lengths.len())];
+                let (mut state, mut string) = (0, String::new());
+
+                while remaining > 0 {
+                    let choices: Vec<(char, usize)> = self
+                        .alphabet
+                        .iter()
+                        .filter_map(|&c| Some((c, self.dfa.next(state, c)?)))
+                        .filter(|&(_, next)| exact[remaining - 1][next])
+                        .collect();
+
+                    let (c, next) = choices[rng.below(choices.len())];
+                    string.push(c);
+                    state = next;
+                    remaining -= 1;
+                }
+
+                string
+            })
+            .collect()
+    }
+
+    /// Rejected strings one edit away from the accepted `examples`: each with
+    /// its last character dropped, with a character appended, and with one
+    /// character replaced, at most `limit` of them.
+    pub fn near_misses(&self, examples: &[String], limit: usize) -> Vec<String> {
+        let mut misses: Vec<String> = Vec::new();
+
+        for example in examples {
+            let chars: Vec<char> = example.chars().collect();
+            let mut candidates: Vec<String> = Vec::new();
+
+            if !chars.is_empty() {
+                candidates.push(chars[..chars.len() - 1].iter().collect());
+            }
+
+            for &c in &self.alphabet {
+                candidates.push(format!("{}{}", example, c));
+            }
+
+            for i in 0..chars.len() {
+                for &c in &self.alphabet {
+                    let mut replaced = chars.clone();
+                    replaced[i] = c;
+                    candidates.push(replaced.into_iter().collect());
+                }
+            }
+
+            for candidate in candidates {
+                if misses.len() == limit {
+                    return misses;
+                }
+
+                if !self.accepts(&candidate) && !misses.contains(&candidate) {
+                    misses.push(candidate);
+                }
+            }
+        }
+
+        misses
+    }
+
+    /// Appends to `found` the accepted strings of `length` more characters
+    /// after `prefix`, which leads to `state`, until it holds `n`.
+    fn extend(
+        &mut self,
+        state: usize,
+        length: usize,
+        prefix: &mut String,
+        n: usize,
+        found: &mut Vec<String>,
+    ) {
+        if found.len() == n || !self.accepts_after(state, length) {
+            return;
+        }
+
+        if length == 0 {
+            found.push(prefix.clone());
+            return;
+        }
+
+        for c in self.alphabet.clone() {
+            if let Some(next) = self.dfa.next(state, c) {
+                prefix.push(c);
+                self.extend(next, length - 1, prefix, n, found);
+                prefix.pop();
+            }
+        }
+    }
+
+    /// Whether `state` accepts after exactly `length` more characters.
+    fn accepts_after(&mut self, state: usize, length: usize) -> bool {
+        while self.exact.len() <= length {
+            let last = self.exact.last().unwrap();
+            let next = (0..self.dfa.states.len())
+                .map(|s| {
+                    self.alphabet
+                        .iter()
+                        .any(|&c| self.dfa.next(s, c).is_some_and(|next| last[next]))
+                })
+                .collect();
+
+            self.exact.push(next);
+        }
+
+        self.exact[length][state]
+    }
+}
+
+/// A small xorshift generator, so the same seed always gives the same
+/// examples.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift never leaves zero.
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number from `0` up to but not including `n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn examples(regex: &str, alphabet: &str) -> Examples {
+        Examples::new(&regex.parse().unwrap(), &alphabet.chars().collect())
+    }
+
+    #[test]
+    fn ordered() {
+        assert_eq!(
+            examples("(a|b)*abb", "ab").shortest(),
+            Some(String::from("abb"))
+        );
+        assert_eq!(examples("a*", "ab").shortest(), Some(String::new()));
+        assert_eq!(examples("c", "ab").shortest(), None);
+
+        assert_eq!(
+            examples("(a|b)*b", "ab").first(5),
+            vec!["b", "ab", "bb", "aab", "abb"]
+        );
+        // A finite language runs out.
+        assert_eq!(examples("ab|b|ba", "ab").first(5), vec!["b", "ab", "ba"]);
+        assert_eq!(
+            examples("(aa)+", "a").first(3),
+            vec!["aa", "aaaa", "aaaaaa"]
+        );
+    }
+
+    #[test]
+    fn random() {
+        let mut identifiers = examples("a-c(a-c|0-2)*", "abc012");
+        let mut rng = Rng::new(7);
+        let strings = identifiers.random(50, 6, &mut rng);
+
+        assert_eq!(strings.len(), 50);
+        assert!(strings.iter().all(|s| identifiers.accepts(s)));
+        assert!(strings.iter().all(|s| (1..=6).contains(&s.len())));
+        assert!(strings.iter().collect::<BTreeSet<_>>().len() > 10);
+        assert_eq!(
+            identifiers.random(5, 6, &mut Rng::new(7)),
+            strings[..5].to_vec()
+        );
+        assert!(examples("aaa", "a").random(3, 2, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn near_misses() {
+        let mut digits = examples("0-1+", "01a");
+        let first = digits.first(2);
+
+        assert_eq!(first, vec!["0", "1"]);
+        assert_eq!(digits.near_misses(&first, 10), vec!["", "0a", "a", "1a"]);
+        assert_eq!(digits.near_misses(&first, 2), vec!["", "0a"]);
+    }
+}
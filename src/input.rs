@@ -1,16 +1,24 @@
+use crate::alphabet_translator::alphabet_translator;
+use crate::error::Error;
+use crate::regex;
 use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::iter::FromIterator;
 
 pub type Regex = String;
 pub type ID = String;
 pub type TokenOut = Option<String>;
 
+/// The marker that lets a rule match the empty string, written after the
+/// rest of its line.
+pub const NULLABLE: &str = "!nullable";
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct LexerConfig {
     pub alphabet: BTreeSet<char>,
     pub regexes: Vec<(Regex, ID, TokenOut)>,
+    /// The rules marked `!nullable`.
+    pub nullable: BTreeSet<ID>,
 }
 
 impl LexerConfig {
@@ -22,53 +30,132 @@ impl LexerConfig {
         let mut config = LexerConfig::default();
 
         let alpha_line = lines.next().unwrap();
-        let alphabet = parse_alphabet(alpha_line);
+        let alphabet = parse_alphabet(&alpha_line);
+
+        for line in lines {
+            let (regex, id, token_out, nullable) = parse_regex(line);
+
+            if nullable {
+                config.nullable.insert(id.clone());
+            }
 
-        let regexes = lines.into_iter().map(parse_regex).collect();
+            config.regexes.push((regex, id, token_out));
+        }
 
         config.alphabet = alphabet;
-        config.regexes = regexes;
 
         config
     }
+
+    /// Fails when `regex`, the regex of the rule `id`, matches the empty
+    /// string without being marked `!nullable`. A maximal-munch scanner would
+    /// match λ forever.
+    pub fn check_nullable(&self, id: &str, regex: &regex::Regex) -> Result<(), Error> {
+        match regex.is_nullable() && !self.nullable.contains(id) {
+            true => Err(Error::UnmarkedNullable(id.to_string())),
+            false => Ok(()),
+        }
+    }
 }
 
-fn parse_regex(line: String) -> (Regex, ID, TokenOut) {
+#[cfg(test)]
+impl LexerConfig {
+    /// A config of `(regex, id)` rules without token outputs.
+    pub(crate) fn from_rules(alphabet: &str, rules: &[(&str, &str)]) -> Self {
+        LexerConfig {
+            alphabet: alphabet.chars().collect(),
+            regexes: rules
+                .iter()
+                .map(|(regex, id)| (regex.to_string(), id.to_string(), None))
+                .collect(),
+            ..LexerConfig::default()
+        }
+    }
+
+    /// The rules of scan.lut, leaving out the token output of `dee`.
+    pub(crate) fn scan() -> Self {
+        LexerConfig::from_rules(
+            "\n \\abcde",
+            &[
+                ("(\\s|\\\\|b|c|d)*a", "lasta"),
+                ("(\\s|a|c|d)*b", "lastb"),
+                ("(\\s|\\\\|a|b|d)*c", "lastc"),
+                ("d+", "dee"),
+                (".", "IGNORE"),
+            ],
+        )
+    }
+}
+
+fn parse_regex(line: String) -> (Regex, ID, TokenOut, bool) {
     let line = line.trim();
     let mut split = line.split_ascii_whitespace();
 
     let regex = split.next().unwrap().to_string();
     let id = split.next().unwrap().to_string();
-    let token_out = split.next().map(ToOwned::to_owned);
-
-    (regex, id, token_out)
-}
+    let rest: Vec<&str> = split.collect();
 
-fn parse_alphabet(line: String) -> BTreeSet<char> {
-    let mut chars = line.chars().filter(|c| !c.is_ascii_whitespace());
+    let nullable = rest.contains(&NULLABLE);
+    let token_out = rest
+        .into_iter()
+        .find(|word| *word != NULLABLE)
+        .map(ToOwned::to_owned);
 
-    let mut alpha = BTreeSet::new();
+    (regex, id, token_out, nullable)
+}
 
-    while let Some(c) = decode_char(&mut chars) {
-        alpha.insert(c);
-    }
+/// Reads an alphabet written like the first line of a `.lut` file.
+pub fn parse_alphabet(line: &str) -> BTreeSet<char> {
+    let line: String = line.chars().filter(|c| !c.is_ascii_whitespace()).collect();
 
-    alpha
+    alphabet_translator::decode(&line).into_iter().collect()
 }
 
-fn decode_char(chars: &mut dyn Iterator<Item = char>) -> Option<char> {
-    Some(match chars.next()? {
-        'x' => {
-            let first = chars.next()?;
-            let second = chars.next()?;
-            let mut first = first.to_string();
-            first.push(second);
-
-            let value: u32 = u32::from_str_radix(&first, 16).unwrap();
-            let char = std::char::from_u32(value).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let rule = |line: &str| parse_regex(line.to_string());
+
+        assert_eq!(
+            rule("d+   dee   D"),
+            (
+                String::from("d+"),
+                String::from("dee"),
+                Some(String::from("D")),
+                false
+            )
+        );
+        assert_eq!(
+            rule("a*  as  !nullable"),
+            (String::from("a*"), String::from("as"), None, true)
+        );
+        assert_eq!(
+            rule("a*  as  A  !nullable"),
+            (
+                String::from("a*"),
+                String::from("as"),
+                Some(String::from("A")),
+                true
+            )
+        );
+    }
 
-            char
-        }
-        c => c,
-    })
+    #[test]
+    fn nullable_rules() {
+        let mut config = LexerConfig::default();
+        config.nullable.insert(String::from("marked"));
+        let regex: regex::Regex = "a*".parse().unwrap();
+
+        assert!(matches!(
+            config.check_nullable("unmarked", &regex),
+            Err(Error::UnmarkedNullable(id)) if id == "unmarked"
+        ));
+        assert!(config.check_nullable("marked", &regex).is_ok());
+        assert!(config
+            .check_nullable("unmarked", &"a+".parse().unwrap())
+            .is_ok());
+    }
 }
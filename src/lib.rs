@@ -1,12 +1,35 @@
 use silly_lex::{Lexer, Token};
 
 pub mod alphabet_translator;
+pub mod analysis;
 pub mod ast;
 pub mod cfg;
+pub mod cfg_builder;
+pub mod cfg_parser;
+pub mod cfg_writer;
+pub mod char_set;
+pub mod classes;
+pub mod cnf;
+pub mod codegen;
+pub mod compress;
+pub mod cyk;
+pub mod dfa;
+pub mod differential;
+pub mod ebnf;
+pub mod equiv;
 pub mod error;
+pub mod examples;
 pub mod input;
+pub mod lint;
 pub mod ll_table;
+pub mod llre;
 pub mod nfa_generator;
+pub mod optimize;
+pub mod overlap;
 pub mod parser;
 pub mod production;
+pub mod reference_table;
+pub mod regex;
+pub mod rewrite;
 pub mod symbol;
+pub mod utf8;
@@ -0,0 +1,200 @@
+use crate::ast::{AstKind, AstNode};
+use crate::char_set::CharSet;
+use crate::dfa::Dfa;
+use crate::input::{LexerConfig, NULLABLE};
+use crate::llre;
+use crate::overlap::{conflicts, Conflict};
+use crate::parser::Parser;
+use crate::regex::Regex;
+use crate::rewrite::Rewriter;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A likely mistake in a `.lut` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    /// `start-end` is backwards and matches nothing.
+    ReversedRange {
+        token: String,
+        start: char,
+        end: char,
+    },
+    /// The regex of `token` names characters the alphabet does not declare.
+    UndeclaredChars { token: String, chars: CharSet },
+    /// `token` matches the empty string without being marked `!nullable`.
+    Nullable { token: String },
+    /// `count` rules are called `token`, so each writes over the `.nfa` of
+    /// the one before.
+    DuplicateId { token: String, count: usize },
+    /// An earlier rule matches everything `token` does.
+    Shadowed { token: String, by: String },
+    /// No token can consume these characters of the alphabet.
+    UnusedChars(CharSet),
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::ReversedRange { token, start, end } => write!(
+                f,
+                "{}: the range {}-{} is reversed and matches nothing",
+                token,
+                start.escape_debug(),
+                end.escape_debug()
+            ),
+            Lint::UndeclaredChars { token, chars } => {
+                write!(f, "{}: {} are not in the alphabet", token, chars)
+            }
+            Lint::Nullable { token } => write!(
+                f,
+                "{}: matches the empty string but is not marked {}",
+                token, NULLABLE
+            ),
+            Lint::DuplicateId { token, count } => {
+                write!(f, "{}: {} rules share this id", token, count)
+            }
+            Lint::Shadowed { token, by } => {
+                write!(f, "{}: shadowed by {} and can never be produced", token, by)
+            }
+            Lint::UnusedChars(chars) => write!(f, "no token consumes {}", chars),
+        }
+    }
+}
+
+/// Runs every check on `config`, rule by rule and then across rules.
+pub fn lint(config: &LexerConfig) -> Result<Vec<Lint>> {
+    let parser = Parser::new(&llre::GRAMMAR, &llre::TABLE);
+    let alphabet: CharSet = config.alphabet.iter().collect();
+    let mut lints = Vec::new();
+    let mut consumed = CharSet::new();
+    let mut ids: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (regex, id, _) in &config.regexes {
+        let mut lexer = silly_lex::Lexer::new(regex).iter().peekable();
+        let ast = Rewriter::regex()
+            .parse(&parser, &mut lexer)
+            .with_context(|| format!("Could not parse the regex for {}", id))?;
+
+        for (start, end) in reversed_ranges(&ast) {
+            lints.push(Lint::ReversedRange {
+                token: id.clone(),
+                start,
+                end,
+            });
+        }
+
+        let regex = Regex::try_from(&ast)
+            .with_context(|| format!("Could not build the regex for {}", id))?;
+        let undeclared = regex.chars().difference(&alphabet);
+
+        if !undeclared.is_empty() {
+            lints.push(Lint::UndeclaredChars {
+                token: id.clone(),
+                chars: undeclared,
+            });
+        }
+
+        if config.check_nullable(id, &regex).is_err() {
+            lints.push(Lint::Nullable { token: id.clone() });
+        }
+
+        for state in Dfa::from_regex(&regex, &config.alphabet).states {
+            for (label, _) in state.transitions {
+                consumed = consumed.union(&label);
+            }
+        }
+
+        *ids.entry(id).or_default() += 1;
+    }
+
+    for (id, count) in ids {
+        if count > 1 {
+            lints.push(Lint::DuplicateId {
+                token: id.to_string(),
+                count,
+            });
+        }
+    }
+
+    for conflict in conflicts(config)? {
+        // `DuplicateId` already covers a rule shadowed by its namesake.
+        if let Conflict::Shadowed { token, by } = conflict {
+            if token != by {
+                lints.push(Lint::Shadowed { token, by });
+            }
+        }
+    }
+
+    let unused = alphabet.difference(&consumed);
+
+    if !unused.is_empty() {
+        lints.push(Lint::UnusedChars(unused));
+    }
+
+    Ok(lints)
+}
+
+/// The ranges of a simplified AST whose end comes before their start.
+fn reversed_ranges(node: &AstNode) -> Vec<(char, char)> {
+    let mut ranges = Vec::new();
+
+    if let (AstKind::CharRng, [start, end]) = (node.kind, node.children.as_slice()) {
+        if let (AstKind::Char(start), AstKind::Char(end)) = (start.kind, end.kind) {
+            if start > end {
+                ranges.push((start, end));
+            }
+        }
+    }
+
+    for child in &node.children {
+        ranges.extend(reversed_ranges(child));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean() {
+        let scan = LexerConfig::scan();
+
+        assert_eq!(lint(&scan).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn problems() {
+        let mut rules = LexerConfig::from_rules(
+            "abcxyz",
+            &[
+                ("a-c|z-x", "ID"),
+                ("aq", "AQ"),
+                ("c", "ID"),
+                ("a*", "AS"),
+                ("(b|c)*", "BS"),
+            ],
+        );
+        rules.nullable.insert(String::from("BS"));
+
+        let found: Vec<String> = lint(&rules)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                "ID: the range z-x is reversed and matches nothing",
+                "AQ: [q] are not in the alphabet",
+                "AS: matches the empty string but is not marked !nullable",
+                "ID: 2 rules share this id",
+                "no token consumes [x-z]",
+            ]
+        );
+    }
+}
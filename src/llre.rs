@@ -0,0 +1,75 @@
+//! The regex grammar `llre.cfg` and its LL(1) table, compiled in by `build.rs`.
+
+use crate::cfg::CFG;
+use crate::ll_table::LLTable;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use lazy_static::lazy_static;
+
+/// A symbol of `llre.cfg` as `build.rs` writes it, already sorted into
+/// terminals and non-terminals by the grammar parser.
+#[derive(Debug, Clone, Copy)]
+enum GeneratedSymbol {
+    Terminal(&'static str),
+    NonTerminal(&'static str),
+    Lambda,
+}
+
+impl GeneratedSymbol {
+    fn symbol(self) -> Symbol {
+        match self {
+            GeneratedSymbol::Terminal(t) => Symbol::from_terminal(Terminal::new(t)),
+            GeneratedSymbol::NonTerminal(nt) => Symbol::from_non_terminal(NonTerminal::new(nt)),
+            GeneratedSymbol::Lambda => Symbol::Lambda,
+        }
+    }
+}
+
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/llre_table.rs"));
+}
+
+lazy_static! {
+    pub static ref GRAMMAR: CFG = {
+        let mut cfg = CFG::new();
+
+        for (lhs, rhs, action) in generated::PRODUCTIONS {
+            let symbols = rhs.iter().map(|s| s.symbol()).collect();
+            let idx = cfg.add_production(NonTerminal::new(*lhs), symbols);
+
+            if let Some(label) = action {
+                cfg.actions.insert(idx, label.to_string());
+            }
+        }
+
+        cfg.start_symbol = NonTerminal::new(generated::START);
+        cfg
+    };
+    pub static ref TABLE: LLTable<'static> = {
+        let table = generated::TABLE
+            .iter()
+            .map(|(nt, row)| {
+                let nt = GRAMMAR.non_terminals.get(&NonTerminal::new(*nt)).unwrap();
+                let row = row
+                    .iter()
+                    .map(|(t, cell)| (Terminal::new(*t), *cell))
+                    .collect();
+                (nt, row)
+            })
+            .collect();
+
+        LLTable { table }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_grammar_file() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+
+        assert_eq!(*GRAMMAR, cfg);
+        assert_eq!(*TABLE, LLTable::from_cfg(&cfg));
+    }
+}
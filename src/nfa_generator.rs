@@ -1,7 +1,7 @@
 use crate::alphabet_translator::*;
-use crate::ast::AstKind;
+use crate::char_set::CharSet;
 /// This takes in a perfectly simplified Regex tree and creates an NFA
-use crate::ast::AstNode;
+use crate::regex::Regex;
 use std::io::prelude::*;
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -11,10 +11,9 @@ use std::{
 pub struct NFAGenerator {
     // pub root: AstNode,
     // This is the "T" from here: https://cs.mcprogramming.com/static/comp/hr/2290bf6e443cd3c7/lga-re-semantic-analysis.pdf
-    // First usize: leftmost col
-    // char, top most char
-    // third usize is the number inside the table
-    pub transitions: BTreeMap<(usize, char), usize>,
+    // Keyed by the (from, to) states, each edge is labelled with the set of
+    // characters it reads, so a range or `.` is a single edge.
+    pub transitions: BTreeMap<(usize, usize), CharSet>,
 
     // same pattern as transitions, but top is number not char?
     pub lambda_transitions: BTreeMap<(usize, usize), bool>,
@@ -51,29 +50,14 @@ impl NFAGenerator {
         let mut output_lines: Vec<String> = Vec::new();
         let lambda_char = self.find_lambda_char().unwrap();
 
-        let mut added_ts: BTreeSet<(usize, &usize)> = BTreeSet::new();
-
-        for t in &self.transitions {
+        for (&(from, to), label) in &self.transitions {
             let mut output_line = String::new();
-            let from = (t.0).0;
-            let to = t.1;
-            if added_ts.contains(&(from, to)) {
-                // println!("skipping {:?}", &(from, to));
-                continue;
-            } else {
-                added_ts.insert((from, to));
-            }
-            // println!("Dealing with {:#?}", t);
+            let to = &to;
 
-            let mut all_chars_for_this_trans: Vec<char> = self
-                .transitions
-                .iter()
-                .filter(|maybe| (maybe.0).0 == from && (maybe.1) == to)
-                .map(|c| (c.0).1)
-                .collect();
-            // all_chars_for_this_trans.push((t.0).1);
+            // Characters outside the alphabet are written too, like any other,
+            // so an edge is never left without a label.
+            let all_chars_for_this_trans: Vec<char> = label.chars().collect();
 
-            // println!("All chars for this trans: {:?}", all_chars_for_this_trans);
             let has_lambda = *self.lambda_transitions.get(&(from, *to)).unwrap_or(&false);
 
             if from == 1 {
@@ -122,7 +106,7 @@ impl NFAGenerator {
             output_line.push(' ');
             output_line.push_str(&to.to_string());
             output_line.push(' ');
-            output_line.push(lambda_char);
+            output_line.push_str(&alphabet_translator::char_to_hex_if_whitespace(lambda_char));
             output_lines.push(output_line);
         }
 
@@ -138,7 +122,7 @@ impl NFAGenerator {
         let mut first_line = String::new();
         first_line.push_str(&output_lines.len().to_string());
         first_line.push(' ');
-        first_line.push(lambda_char);
+        first_line.push_str(&alphabet_translator::char_to_hex_if_whitespace(lambda_char));
         first_line.push(' ');
 
         let mut ordered_alpha: Vec<&char> = self.alpha.iter().collect();
@@ -169,12 +153,22 @@ impl NFAGenerator {
             .filter(|c| c.is_alphabetic()) // Filter only alphabetic chars
             .collect::<Vec<_>>(); // Collect as Vec<char>
 
+        // An edge may read characters outside the alphabet, lambda must not be one of them.
+        let used = |c: &char| {
+            self.alpha.contains(c) || self.transitions.values().any(|label| label.contains(*c))
+        };
+
         for c in &alphabet {
-            if !&self.alpha.contains(c) {
+            if !used(c) {
                 return Ok(*c);
             }
         }
-        Err(()) // this should only really happen if the alphabet is full, and I guess a panic is okay
+
+        // A byte alphabet has every letter, so fall back to the characters
+        // after the 256 bytes.
+        ('\u{100}'..=char::MAX)
+            .find(|c| !used(c))
+            .ok_or(()) // this should only really happen if the alphabet is full, and I guess a panic is okay
     }
 
     pub fn get_new_state(&mut self) -> usize {
@@ -182,79 +176,102 @@ impl NFAGenerator {
         self.highest_state_number
     }
 
-    pub fn insert_to_trans(&mut self, current: usize, next: usize, value: char) {
-        self.transitions.insert((current, value), next);
+    pub fn insert_to_trans(&mut self, current: usize, next: usize, chars: &CharSet) {
+        let label = self.transitions.entry((current, next)).or_default();
+        *label = label.union(chars);
     }
 
     /// Returns weather or not a change has been made. `curent_state` is "this" in psuedocode, `next_state` is next
-    pub fn add_to_table(
-        &mut self,
-        node: &AstNode,
-        current_state: usize,
-        next_state: usize,
-    ) -> bool {
-        match node.kind {
-            AstKind::Char(c) => self.leaf_child(c, current_state, next_state),
-            AstKind::Dot => self.leaf_dot(current_state, next_state),
-            AstKind::Lambda => self.leaf_lambda(current_state, next_state),
-            AstKind::Alt => self.node_alt(node, current_state, next_state),
-            AstKind::Kleene => self.node_kleene(node, current_state, next_state),
-            AstKind::Seq => self.node_seq(node, current_state, next_state),
-
-            _ => panic!("Bad AST node kind in add_to_table: #{:?}", node.kind),
+    pub fn add_to_table(&mut self, regex: &Regex, current_state: usize, next_state: usize) -> bool {
+        match regex {
+            Regex::Char(c) => self.leaf_child(*c, current_state, next_state),
+            Regex::Set(chars) => self.insert_to_trans(current_state, next_state, chars),
+            Regex::Any => self.leaf_dot(current_state, next_state),
+            Regex::Empty => self.leaf_lambda(current_state, next_state),
+            Regex::Alt(regexes) => self.node_alt(regexes, current_state, next_state),
+            Regex::Star(regex) => self.node_kleene(regex, current_state, next_state),
+            Regex::Concat(regexes) => self.node_seq(regexes, current_state, next_state),
+            Regex::Plus(regex) => self.node_plus(regex, current_state, next_state),
+            Regex::Optional(regex) => self.node_alt(
+                &[(**regex).clone(), Regex::Empty],
+                current_state,
+                next_state,
+            ),
+            Regex::Repeat { regex, min, max } => {
+                let mut parts = vec![(**regex).clone(); *min];
+
+                match max {
+                    Some(max) => parts.extend(std::iter::repeat_n(
+                        Regex::Optional(regex.clone()),
+                        max.saturating_sub(*min),
+                    )),
+                    None => parts.push(Regex::Star(regex.clone())),
+                }
+
+                self.node_seq(&parts, current_state, next_state)
+            }
         }
         false
     }
 
-    pub fn node_seq(&mut self, node: &AstNode, this: usize, next: usize) {
+    pub fn node_seq(&mut self, children: &[Regex], this: usize, next: usize) {
         // println!("in node seq");
-        if node.children.len() == 1 {
-            self.add_to_table(&node.children[0], this, next);
+        if children.is_empty() {
+            self.leaf_lambda(this, next);
+            return;
+        }
+        if children.len() == 1 {
+            self.add_to_table(&children[0], this, next);
             return;
         }
-        let new_states: Vec<usize> = (0..node.children.len() - 1)
+        let new_states: Vec<usize> = (0..children.len() - 1)
             .map(|_| self.get_new_state())
             .collect();
 
-        self.add_to_table(&node.children[0], this, new_states[0]);
-        for i in 1..node.children.len() - 1 {
-            self.add_to_table(&node.children[i], new_states[i - 1], new_states[i]);
+        self.add_to_table(&children[0], this, new_states[0]);
+        for i in 1..children.len() - 1 {
+            self.add_to_table(&children[i], new_states[i - 1], new_states[i]);
         }
 
-        self.add_to_table(
-            node.children.last().unwrap(),
-            *new_states.last().unwrap(),
-            next,
-        );
+        self.add_to_table(children.last().unwrap(), *new_states.last().unwrap(), next);
     }
 
     pub fn leaf_child(&mut self, value: char, this: usize, next: usize) {
-        self.insert_to_trans(this, next, value);
+        self.insert_to_trans(this, next, &CharSet::single(value));
     }
 
     pub fn leaf_dot(&mut self, this: usize, next: usize) {
-        for c in &self.alpha {
-            // have to inline this instead of calling insert_to_trans because borrow checking :(
-            self.transitions.insert((this, c.clone()), next);
-        }
+        let alphabet: CharSet = self.alpha.iter().collect();
+        self.insert_to_trans(this, next, &alphabet);
     }
 
     pub fn leaf_lambda(&mut self, this: usize, next: usize) {
         self.lambda_transitions.insert((this, next), true);
     }
 
-    pub fn node_alt(&mut self, node: &AstNode, this: usize, next: usize) {
-        for i in 0..node.children.len() {
+    pub fn node_alt(&mut self, children: &[Regex], this: usize, next: usize) {
+        for child in children {
             let new = self.get_new_state();
             self.lambda_transitions.insert((this, new), true);
-            self.add_to_table(&node.children[i], new, next);
+            self.add_to_table(child, new, next);
         }
     }
 
-    pub fn node_kleene(&mut self, node: &AstNode, this: usize, next: usize) {
+    /// Like `node_kleene`, but `regex` has to be passed once before the loop
+    /// back, without building it twice.
+    pub fn node_plus(&mut self, regex: &Regex, this: usize, next: usize) {
+        let start = self.get_new_state();
+        let end = self.get_new_state();
+        self.lambda_transitions.insert((this, start), true);
+        self.add_to_table(regex, start, end);
+        self.lambda_transitions.insert((end, start), true);
+        self.lambda_transitions.insert((end, next), true);
+    }
+
+    pub fn node_kleene(&mut self, regex: &Regex, this: usize, next: usize) {
         let out = self.get_new_state();
         self.lambda_transitions.insert((this, out), true);
-        self.add_to_table(&node.children[0], out, out);
+        self.add_to_table(regex, out, out);
         self.lambda_transitions.insert((out, next), true);
 
         // 12/13 tests pass with this commented in
@@ -270,12 +287,62 @@ impl NFAGenerator {
         // self.lambda_transitions.insert((this, out), true);
         // self.lambda_transitions.insert((out, this), true);
     }
+
+    /// Runs the NFA from `start` on `input` by tracking every state it can be
+    /// in, and says whether `accept` is one of them at the end.
+    pub fn accepts(&self, start: usize, accept: usize, input: &str) -> bool {
+        let closure = |mut states: BTreeSet<usize>| {
+            let mut stack: Vec<usize> = states.iter().copied().collect();
+
+            while let Some(state) = stack.pop() {
+                for (&(from, to), &is_lambda) in &self.lambda_transitions {
+                    if from == state && is_lambda && states.insert(to) {
+                        stack.push(to);
+                    }
+                }
+            }
+
+            states
+        };
+
+        let mut states = closure(std::iter::once(start).collect());
+
+        for c in input.chars() {
+            let next = self
+                .transitions
+                .iter()
+                .filter(|((from, _), label)| states.contains(from) && label.contains(c))
+                .map(|(&(_, to), _)| to)
+                .collect();
+
+            states = closure(next);
+        }
+
+        states.contains(&accept)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::ast::{AstKind, AstNode};
+    use std::convert::TryFrom;
+
+    fn regex(node: &AstNode) -> Regex {
+        Regex::try_from(node).unwrap()
+    }
+
+    fn edges(edges: &[(usize, char, usize)]) -> BTreeMap<(usize, usize), CharSet> {
+        let mut transitions: BTreeMap<(usize, usize), CharSet> = BTreeMap::new();
+
+        for &(from, c, to) in edges {
+            let label = transitions.entry((from, to)).or_default();
+            *label = label.union(&CharSet::single(c));
+        }
+
+        transitions
+    }
 
     #[test]
     fn basic_output_file() {
@@ -289,7 +356,7 @@ mod tests {
         r.children.push(a);
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, Some(String::from("simple")));
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         // just adding thing to test output
 
@@ -299,6 +366,23 @@ mod tests {
         simple.create_output_files();
     }
 
+    #[test]
+    fn undeclared_chars_are_written() {
+        let alphabet: BTreeSet<char> = ['a', 'b'].iter().cloned().collect();
+        let token = std::env::temp_dir().join("wreck_undeclared");
+        let token = token.to_str().unwrap().to_string();
+
+        let mut nfa = NFAGenerator::new(alphabet, Some(token.clone()));
+        nfa.add_to_table(&"aA".parse().unwrap(), 0, 1);
+        nfa.create_output_files().unwrap();
+
+        // `A` is read by an edge, so lambda has to be another character.
+        assert_eq!(
+            std::fs::read_to_string(token + ".nfa").unwrap(),
+            "3 B a b \n- 0 2 a \n- 2 1 A \n+ 1 1\n"
+        );
+    }
+
     #[test]
     fn test_simple_kleene() {
         let a_to_e_alpha: BTreeSet<char> = ['a', 'b', 'c', 'd', 'e'].iter().cloned().collect();
@@ -306,15 +390,14 @@ mod tests {
         r.children.push(AstNode::new(AstKind::Char('b')));
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         let mut expected_l = BTreeMap::new();
-        expected_l.insert((0, 1), true);
-        expected_l.insert((1, 0), true);
+        expected_l.insert((0, 2), true);
+        expected_l.insert((2, 1), true);
 
         assert_eq!(simple.lambda_transitions, expected_l);
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 1);
+        let expected_t = edges(&[(2, 'b', 2)]);
         assert_eq!(simple.transitions, expected_t);
     }
 
@@ -330,17 +413,17 @@ mod tests {
         r.children.push(a);
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         let mut expected_l = BTreeMap::new();
-        expected_l.insert((0, 1), true);
-        expected_l.insert((1, 0), true);
+        expected_l.insert((0, 2), true);
+        expected_l.insert((2, 3), true);
+        expected_l.insert((2, 4), true);
+        expected_l.insert((2, 5), true);
+        expected_l.insert((2, 1), true);
 
         assert_eq!(simple.lambda_transitions, expected_l);
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 1);
-        expected_t.insert((0, 'c'), 1);
-        expected_t.insert((0, 'd'), 1);
+        let expected_t = edges(&[(3, 'b', 2), (4, 'c', 2), (5, 'd', 2)]);
         assert_eq!(simple.transitions, expected_t);
     }
 
@@ -356,17 +439,14 @@ mod tests {
         r.children.push(a);
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         let mut expected_l = BTreeMap::new();
-        expected_l.insert((0, 1), true);
-        expected_l.insert((1, 0), true);
+        expected_l.insert((0, 2), true);
+        expected_l.insert((2, 1), true);
 
         assert_eq!(simple.lambda_transitions, expected_l);
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 2);
-        expected_t.insert((2, 'c'), 3);
-        expected_t.insert((3, 'd'), 1);
+        let expected_t = edges(&[(2, 'b', 3), (3, 'c', 4), (4, 'd', 2)]);
         assert_eq!(simple.transitions, expected_t);
     }
 
@@ -379,15 +459,16 @@ mod tests {
         r.children.push(AstNode::new(AstKind::Char('d')));
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 1);
-        expected_t.insert((0, 'c'), 1);
-        expected_t.insert((0, 'd'), 1);
+        let expected_t = edges(&[(2, 'b', 1), (3, 'c', 1), (4, 'd', 1)]);
+        let mut expected_l = BTreeMap::new();
+        expected_l.insert((0, 2), true);
+        expected_l.insert((0, 3), true);
+        expected_l.insert((0, 4), true);
 
         assert_eq!(simple.transitions, expected_t);
-        assert_eq!(simple.lambda_transitions, BTreeMap::new());
+        assert_eq!(simple.lambda_transitions, expected_l);
     }
     #[test]
     fn test_lambda() {
@@ -395,7 +476,7 @@ mod tests {
         let a_to_e_alpha: BTreeSet<char> = ['a', 'b', 'c', 'd', 'e'].iter().cloned().collect();
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         let mut expected_l = BTreeMap::new();
         expected_l.insert((0, 1), true);
@@ -413,13 +494,11 @@ mod tests {
         let a_to_e_alpha: BTreeSet<char> = ['a', 'b', 'c', 'd', 'e'].iter().cloned().collect();
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
         let expected_l = BTreeMap::new();
 
-        let mut expected_t = BTreeMap::new();
-
-        expected_t.insert((0, 'b'), 1);
+        let expected_t = edges(&[(0, 'b', 1)]);
 
         assert_eq!(simple.lambda_transitions, expected_l);
         assert_eq!(simple.transitions, expected_t);
@@ -432,14 +511,16 @@ mod tests {
         let a_to_e_alpha: BTreeSet<char> = ['a', 'b', 'c', 'd', 'e'].iter().cloned().collect();
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'a'), 1);
-        expected_t.insert((0, 'b'), 1);
-        expected_t.insert((0, 'c'), 1);
-        expected_t.insert((0, 'd'), 1);
-        expected_t.insert((0, 'e'), 1);
+        let expected_t = edges(&[
+            (0, 'a', 1),
+            (0, 'b', 1),
+            (0, 'c', 1),
+            (0, 'd', 1),
+            (0, 'e', 1),
+        ]);
+        assert_eq!(simple.transitions[&(0, 1)].ranges(), &[('a', 'e')]);
 
         assert_eq!(simple.lambda_transitions, BTreeMap::new());
         assert_eq!(simple.transitions, expected_t);
@@ -453,11 +534,9 @@ mod tests {
         r.children.push(AstNode::new(AstKind::Char('c')));
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 2);
-        expected_t.insert((2, 'c'), 1);
+        let expected_t = edges(&[(0, 'b', 2), (2, 'c', 1)]);
 
         assert_eq!(simple.lambda_transitions, BTreeMap::new());
         assert_eq!(simple.transitions, expected_t);
@@ -473,15 +552,45 @@ mod tests {
         r.children.push(AstNode::new(AstKind::Char('e')));
 
         let mut simple = NFAGenerator::new(a_to_e_alpha, None);
-        simple.add_to_table(&r, 0, 1);
+        simple.add_to_table(&regex(&r), 0, 1);
 
-        let mut expected_t = BTreeMap::new();
-        expected_t.insert((0, 'b'), 2);
-        expected_t.insert((2, 'c'), 3);
-        expected_t.insert((3, 'd'), 4);
-        expected_t.insert((4, 'e'), 1);
+        let expected_t = edges(&[(0, 'b', 2), (2, 'c', 3), (3, 'd', 4), (4, 'e', 1)]);
 
         assert_eq!(simple.lambda_transitions, BTreeMap::new());
         assert_eq!(simple.transitions, expected_t);
     }
+
+    #[test]
+    fn test_typed_regexes() {
+        let a_to_e_alpha: BTreeSet<char> = ['a', 'b', 'c', 'd', 'e'].iter().cloned().collect();
+        let set = Regex::Set(['b', 'c'].iter().cloned().collect());
+
+        let mut simple = NFAGenerator::new(a_to_e_alpha.clone(), None);
+        simple.add_to_table(&Regex::optional(set), 0, 1);
+
+        let expected_t = edges(&[(2, 'b', 1), (2, 'c', 1)]);
+        let mut expected_l = BTreeMap::new();
+        expected_l.insert((0, 2), true);
+        expected_l.insert((0, 3), true);
+        expected_l.insert((3, 1), true);
+
+        assert_eq!(simple.transitions, expected_t);
+        assert_eq!(simple.lambda_transitions, expected_l);
+
+        let repeat = Regex::Repeat {
+            regex: Box::new(Regex::Char('a')),
+            min: 2,
+            max: None,
+        };
+        let mut simple = NFAGenerator::new(a_to_e_alpha, None);
+        simple.add_to_table(&repeat, 0, 1);
+
+        let expected_t = edges(&[(0, 'a', 2), (2, 'a', 3), (4, 'a', 4)]);
+        let mut expected_l = BTreeMap::new();
+        expected_l.insert((3, 4), true);
+        expected_l.insert((4, 1), true);
+
+        assert_eq!(simple.transitions, expected_t);
+        assert_eq!(simple.lambda_transitions, expected_l);
+    }
 }
@@ -0,0 +1,304 @@
+use crate::char_set::CharSet;
+use crate::regex::Regex;
+
+/// A rewrite of one node whose children have already been rewritten.
+pub type Pass = fn(Regex) -> Regex;
+
+/// The passes `optimize` runs, in order.
+pub const PASSES: &[(&str, Pass)] = &[
+    ("flatten", flatten),
+    ("merge_sets", merge_sets),
+    ("collapse_stars", collapse_stars),
+    ("share_plus", share_plus),
+    ("factor_prefixes", factor_prefixes),
+];
+
+/// Runs every pass bottom-up until the regex stops changing. The result
+/// matches the same strings with fewer NFA states.
+pub fn optimize(mut regex: Regex) -> Regex {
+    loop {
+        let next = rewrite(regex.clone(), &|r| {
+            PASSES.iter().fold(r, |r, (_, pass)| pass(r))
+        });
+
+        if next == regex {
+            return regex;
+        }
+
+        regex = next;
+    }
+}
+
+/// Applies `pass` to every node of `regex`, children first.
+pub fn rewrite(regex: Regex, pass: &dyn Fn(Regex) -> Regex) -> Regex {
+    let child = |r: Box<Regex>| Box::new(rewrite(*r, pass));
+    let children = |rs: Vec<Regex>| rs.into_iter().map(|r| rewrite(r, pass)).collect();
+
+    pass(match regex {
+        Regex::Concat(rs) => Regex::Concat(children(rs)),
+        Regex::Alt(rs) => Regex::Alt(children(rs)),
+        Regex::Star(r) => Regex::Star(child(r)),
+        Regex::Plus(r) => Regex::Plus(child(r)),
+        Regex::Optional(r) => Regex::Optional(child(r)),
+        Regex::Repeat { regex, min, max } => Regex::Repeat {
+            regex: child(regex),
+            min,
+            max,
+        },
+        leaf => leaf,
+    })
+}
+
+/// Splices nested sequences and alternatives into their parent, drops empty
+/// strings from sequences and repeated alternatives, and unwraps the ones
+/// left with a single member.
+pub fn flatten(regex: Regex) -> Regex {
+    match regex {
+        Regex::Concat(rs) => {
+            let mut flat = Vec::new();
+
+            for r in rs {
+                match r {
+                    Regex::Concat(inner) => flat.extend(inner),
+                    Regex::Empty => {}
+                    r => flat.push(r),
+                }
+            }
+
+            match flat.len() {
+                0 => Regex::Empty,
+                1 => flat.pop().unwrap(),
+                _ => Regex::Concat(flat),
+            }
+        }
+        Regex::Alt(rs) => {
+            let mut flat: Vec<Regex> = Vec::new();
+
+            for r in rs {
+                let members = match r {
+                    Regex::Alt(inner) => inner,
+                    r => vec![r],
+                };
+
+                for r in members {
+                    if !flat.contains(&r) {
+                        flat.push(r);
+                    }
+                }
+            }
+
+            match flat.len() {
+                1 => flat.pop().unwrap(),
+                _ => Regex::Alt(flat),
+            }
+        }
+        Regex::Set(chars) if chars.len() == 1 => Regex::Char(chars.ranges()[0].0),
+        regex => regex,
+    }
+}
+
+/// Merges the single characters and sets among alternatives into one set,
+/// `a|b|c-e` becomes `Set(a-e)`.
+pub fn merge_sets(regex: Regex) -> Regex {
+    let rs = match regex {
+        Regex::Alt(rs) => rs,
+        regex => return regex,
+    };
+
+    let is_set = |r: &Regex| matches!(r, Regex::Char(_) | Regex::Set(_));
+
+    if rs.iter().filter(|r| is_set(r)).count() < 2 {
+        return Regex::Alt(rs);
+    }
+
+    let mut chars = CharSet::new();
+    let mut first = None;
+    let mut merged = Vec::new();
+
+    for r in rs {
+        match r {
+            Regex::Char(c) => chars = chars.union(&CharSet::single(c)),
+            Regex::Set(set) => chars = chars.union(&set),
+            r => {
+                merged.push(r);
+                continue;
+            }
+        }
+
+        first.get_or_insert(merged.len());
+    }
+
+    merged.insert(first.unwrap(), Regex::Set(chars));
+
+    match merged.len() {
+        1 => merged.pop().unwrap(),
+        _ => Regex::Alt(merged),
+    }
+}
+
+/// Removes repetition that cannot match anything more: `(r*)*`, `(r+)*`,
+/// `(r?)*`, `(r*)+` and `(r+)?` are all `r*`, and `r*r*` is `r*`.
+pub fn collapse_stars(regex: Regex) -> Regex {
+    match regex {
+        Regex::Star(r) => match *r {
+            Regex::Star(r) | Regex::Plus(r) | Regex::Optional(r) => Regex::Star(r),
+            Regex::Empty => Regex::Empty,
+            r => Regex::star(r),
+        },
+        Regex::Plus(r) => match *r {
+            Regex::Star(r) => Regex::Star(r),
+            Regex::Plus(r) => Regex::Plus(r),
+            r => Regex::plus(r),
+        },
+        Regex::Optional(r) => match *r {
+            Regex::Star(r) | Regex::Plus(r) => Regex::Star(r),
+            Regex::Optional(r) => Regex::Optional(r),
+            r => Regex::optional(r),
+        },
+        Regex::Concat(rs) => {
+            let mut collapsed: Vec<Regex> = Vec::new();
+
+            for r in rs {
+                match (collapsed.last(), &r) {
+                    (Some(Regex::Star(last)), Regex::Star(next)) if last == next => {}
+                    _ => collapsed.push(r),
+                }
+            }
+
+            Regex::Concat(collapsed)
+        }
+        regex => regex,
+    }
+}
+
+/// Turns `r r*` and `r* r` into `r+`, so the NFA builds `r` once
+/// instead of twice. `r` may itself be a sequence, as in `ab(ab)*`.
+pub fn share_plus(regex: Regex) -> Regex {
+    let rs = match regex {
+        Regex::Concat(rs) => rs,
+        regex => return regex,
+    };
+
+    let mut shared: Vec<Regex> = Vec::new();
+
+    for r in rs {
+        let repeated = match &r {
+            Regex::Star(inner) => match inner.as_ref() {
+                Regex::Concat(members) => members.clone(),
+                inner => vec![inner.clone()],
+            },
+            _ => vec![],
+        };
+
+        if !repeated.is_empty() && shared.ends_with(&repeated) {
+            shared.truncate(shared.len() - repeated.len());
+            shared.push(Regex::plus(flatten(Regex::Concat(repeated))));
+            continue;
+        }
+
+        match (shared.last(), &r) {
+            (Some(Regex::Star(inner)), r) if inner.as_ref() == r => {
+                let inner = inner.clone();
+                shared.pop();
+                shared.push(Regex::Plus(inner));
+            }
+            _ => shared.push(r),
+        }
+    }
+
+    match shared.len() {
+        1 => shared.pop().unwrap(),
+        _ => Regex::Concat(shared),
+    }
+}
+
+/// Factors a common first member out of alternatives, `ab|ac|d` becomes
+/// `a(b|c)|d`.
+pub fn factor_prefixes(regex: Regex) -> Regex {
+    let rs = match regex {
+        Regex::Alt(rs) => rs,
+        regex => return regex,
+    };
+
+    let split = |r: Regex| match r {
+        Regex::Concat(mut members) if !members.is_empty() => {
+            let head = members.remove(0);
+            (head, Regex::Concat(members))
+        }
+        r => (r, Regex::Empty),
+    };
+
+    let mut groups: Vec<(Regex, Vec<Regex>)> = Vec::new();
+
+    for (head, rest) in rs.into_iter().map(split) {
+        match groups.iter_mut().find(|(h, _)| *h == head) {
+            Some((_, rests)) => rests.push(rest),
+            None => groups.push((head, vec![rest])),
+        }
+    }
+
+    let factored = groups
+        .into_iter()
+        .map(|(head, mut rests)| match rests.len() {
+            1 => Regex::Concat(vec![head, rests.pop().unwrap()]),
+            _ => Regex::Concat(vec![head, Regex::Alt(rests)]),
+        })
+        .map(flatten)
+        .collect();
+
+    flatten(Regex::Alt(factored))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa_generator::NFAGenerator;
+
+    fn states(regex: &Regex) -> usize {
+        let mut generator = NFAGenerator::new("abcd".chars().collect(), None);
+        generator.add_to_table(regex, 0, 1);
+        generator.highest_state_number
+    }
+
+    #[test]
+    fn passes() {
+        let pass = |pass: Pass, regex: &str| rewrite(regex.parse().unwrap(), &pass).to_string();
+
+        assert_eq!(pass(flatten, "a(bc)((d))"), "abcd");
+        assert_eq!(pass(flatten, "(a|b)|a|()c"), "a|b|c");
+        assert_eq!(pass(merge_sets, "a|bc|c|b-d"), "a-d|bc");
+        assert_eq!(pass(collapse_stars, "(a*)*b*b*"), "a*b*");
+        assert_eq!(pass(share_plus, "a+b*b"), "a+b+");
+        assert_eq!(pass(share_plus, "ab(ab)*"), "(ab)+");
+        assert_eq!(pass(factor_prefixes, "ab|ac|d|a"), "a(b|c|)|d");
+    }
+
+    #[test]
+    fn optimized() {
+        let optimized = |regex: &str| optimize(regex.parse().unwrap()).to_string();
+
+        assert_eq!(optimized("ab|ac|ad"), "ab-d");
+        assert_eq!(optimized("((a+)*)*"), "a*");
+        assert_eq!(optimized("(a|b)+"), "a-b+");
+        assert_eq!(optimized("a-c|d"), "a-d");
+        assert_eq!(optimized(""), "");
+    }
+
+    #[test]
+    fn fewer_states() {
+        for regex in &[
+            "ab|ac|ad",
+            "(a|b|c)(a|b|c)*",
+            "(a*)*b*b*",
+            "ab(ab)*",
+            "a|b|c|d",
+        ] {
+            let regex: Regex = regex.parse().unwrap();
+            assert!(
+                states(&optimize(regex.clone())) < states(&regex),
+                "{}",
+                regex
+            );
+        }
+    }
+}
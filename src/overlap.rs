@@ -0,0 +1,143 @@
+use crate::dfa::Dfa;
+use crate::equiv::product_witness;
+use crate::input::LexerConfig;
+use crate::regex::Regex;
+use anyhow::{Context, Result};
+use std::fmt;
+
+/// A problem between two rules of a `.lut` file. Rules earlier in the file
+/// win the strings both of them match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// Both tokens match `witness`.
+    Overlap {
+        first: String,
+        second: String,
+        witness: String,
+    },
+    /// Every string `token` matches is also matched by the earlier `by`, so
+    /// the scanner never produces it.
+    Shadowed { token: String, by: String },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Overlap {
+                first,
+                second,
+                witness,
+            } => write!(
+                f,
+                "{} and {} overlap, both match {:?}",
+                first, second, witness
+            ),
+            Conflict::Shadowed { token, by } => write!(
+                f,
+                "{} is shadowed by {} and can never be produced",
+                token, by
+            ),
+        }
+    }
+}
+
+/// Intersects the automata of every pair of rules in `config`, in the order
+/// of the file. An overlap comes with the shortest string both rules match.
+pub fn conflicts(config: &LexerConfig) -> Result<Vec<Conflict>> {
+    let dfas = config
+        .regexes
+        .iter()
+        .map(|(regex, id, _)| {
+            let regex: Regex = regex
+                .parse()
+                .with_context(|| format!("Could not parse the regex for {}", id))?;
+            Ok(Dfa::from_regex(&regex, &config.alphabet))
+        })
+        .collect::<Result<Vec<Dfa>>>()?;
+
+    let mut conflicts = Vec::new();
+
+    for (j, later) in dfas.iter().enumerate() {
+        for (i, earlier) in dfas.iter().enumerate().take(j) {
+            let name = |k: usize| config.regexes[k].1.clone();
+            let witness = product_witness(earlier, later, &config.alphabet, |a, b| a && b);
+
+            if let Some(witness) = witness {
+                conflicts.push(Conflict::Overlap {
+                    first: name(i),
+                    second: name(j),
+                    witness,
+                });
+
+                let escapes = product_witness(earlier, later, &config.alphabet, |a, b| !a && b);
+
+                if escapes.is_none() {
+                    conflicts.push(Conflict::Shadowed {
+                        token: name(j),
+                        by: name(i),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_lut() {
+        let scan = LexerConfig::scan();
+        let found: Vec<String> = conflicts(&scan)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                "lasta and IGNORE overlap, both match \"a\"",
+                "lastb and IGNORE overlap, both match \"b\"",
+                "lastc and IGNORE overlap, both match \"c\"",
+                "dee and IGNORE overlap, both match \"d\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed() {
+        let rules = LexerConfig::from_rules("abfi", &[("a-z+", "ID"), ("if", "IF"), ("a|b", "AB")]);
+
+        assert_eq!(
+            conflicts(&rules).unwrap(),
+            vec![
+                Conflict::Overlap {
+                    first: String::from("ID"),
+                    second: String::from("IF"),
+                    witness: String::from("if"),
+                },
+                Conflict::Shadowed {
+                    token: String::from("IF"),
+                    by: String::from("ID"),
+                },
+                Conflict::Overlap {
+                    first: String::from("ID"),
+                    second: String::from("AB"),
+                    witness: String::from("a"),
+                },
+                Conflict::Shadowed {
+                    token: String::from("AB"),
+                    by: String::from("ID"),
+                },
+            ]
+        );
+
+        // The later rule still has strings of its own.
+        let rules = LexerConfig::from_rules("ab", &[("a", "A"), ("a|b", "AB")]);
+        assert_eq!(conflicts(&rules).unwrap().len(), 1);
+    }
+}
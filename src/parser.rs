@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use crate::alphabet_translator::alphabet_translator;
 use crate::ast::{AstKind, AstNode};
 use crate::{cfg::CFG, ll_table::LLTable, symbol::*};
 use derive_more::*;
@@ -12,6 +13,40 @@ pub enum ParseTree {
     Node(AstNode),
 }
 
+/// Builds values bottom-up while parsing, see `Parser::parse_with`.
+pub trait Actions {
+    type Value;
+
+    /// The value of a token the parser consumed for `terminal`.
+    fn token(&mut self, terminal: &Terminal, token: &Token) -> anyhow::Result<Self::Value>;
+
+    /// Called once a production labelled `=> label` has been parsed, with the
+    /// values of its right hand side in order. `$` and lambda have no value.
+    fn reduce(&mut self, label: &str, _children: Vec<Self::Value>) -> anyhow::Result<Self::Value> {
+        anyhow::bail!("Unknown action {:?}", label)
+    }
+
+    /// Called once any production of `non_terminal` has been parsed. A
+    /// labelled production goes to `reduce` and an unlabelled one must have
+    /// exactly one value, which it passes on.
+    fn production(
+        &mut self,
+        non_terminal: &NonTerminal,
+        label: Option<&str>,
+        mut children: Vec<Self::Value>,
+    ) -> anyhow::Result<Self::Value> {
+        match label {
+            Some(label) => self.reduce(label, children),
+            None if children.len() == 1 => Ok(children.pop().unwrap()),
+            None => anyhow::bail!(
+                "A production of {} has {} values but no action to combine them",
+                non_terminal.non_terminal(),
+                children.len()
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Parser<'c, 't> {
     cfg: &'c CFG,
@@ -28,6 +63,88 @@ impl<'c, 't> Parser<'c, 't> {
         self.parse_non_terminal(stream, &root)
     }
 
+    /// Parses the start symbol and calls `actions` for every token and every
+    /// production.
+    pub fn parse_with<A: Actions>(
+        &self,
+        stream: &mut Peekable<impl Iterator<Item = Token>>,
+        actions: &mut A,
+    ) -> anyhow::Result<A::Value> {
+        let value = self.reduce_non_terminal(stream, &self.cfg.start_symbol, actions)?;
+
+        match stream.peek() {
+            Some(token) => Err(anyhow::anyhow!(
+                "Syntax error: unexpected {} {:?} after the end of the input",
+                token.kind,
+                token.data
+            )),
+            None => Ok(value),
+        }
+    }
+
+    fn reduce_non_terminal<A: Actions>(
+        &self,
+        stream: &mut Peekable<impl Iterator<Item = Token>>,
+        non_terminal: &NonTerminal,
+        actions: &mut A,
+    ) -> anyhow::Result<A::Value> {
+        lex_error(stream)?;
+
+        let lookahead = stream
+            .peek()
+            .map_or_else(|| Terminal::new("$"), |t| Terminal::new(t.kind.to_string()));
+
+        let production = self
+            .table
+            .table
+            .get(non_terminal)
+            .and_then(|row| row.get(&lookahead).copied().flatten())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Syntax error: unexpected {} while parsing {}",
+                    lookahead.terminal(),
+                    non_terminal.non_terminal()
+                )
+            })?;
+
+        let mut children = Vec::new();
+
+        for symbol in self.cfg.productions[production].symbols() {
+            match symbol {
+                Symbol::Terminal(t) if t.terminal() == "$" => {
+                    if let Some(token) = stream.peek() {
+                        anyhow::bail!(
+                            "Syntax error: expected the end of the input, found {}",
+                            token.kind
+                        );
+                    }
+                }
+                Symbol::Terminal(t) => {
+                    lex_error(stream)?;
+
+                    match stream.next_if(|token| token.kind.to_string() == t.terminal()) {
+                        Some(token) => children.push(actions.token(t, &token)?),
+                        None => anyhow::bail!(
+                            "Syntax error: expected {}, found {}",
+                            t.terminal(),
+                            stream.peek().map_or_else(
+                                || String::from("the end of the input"),
+                                |t| t.kind.to_string()
+                            )
+                        ),
+                    }
+                }
+                Symbol::NonTerminal(nt) => {
+                    children.push(self.reduce_non_terminal(stream, nt, actions)?)
+                }
+                Symbol::Lambda => {}
+            }
+        }
+
+        let label = self.cfg.actions.get(&production).map(String::as_str);
+        actions.production(non_terminal, label, children)
+    }
+
     fn parse_symbol(
         &self,
         stream: &mut Peekable<impl Iterator<Item = Token>>,
@@ -179,25 +296,17 @@ impl<'c, 't> Parser<'c, 't> {
     // }
 }
 
-fn get_char(data: &str) -> char {
-    decode_char(&mut data.chars()).unwrap()
+pub(crate) fn get_char(data: &str) -> char {
+    alphabet_translator::decode(data)[0]
 }
 
-fn decode_char(chars: &mut dyn Iterator<Item = char>) -> Option<char> {
-    Some(match chars.next()? {
-        'x' => {
-            let first = chars.next()?;
-            let second = chars.next()?;
-            let mut first = first.to_string();
-            first.push(second);
-
-            let value: u32 = u32::from_str_radix(&first, 16).unwrap();
-            let char = std::char::from_u32(value).unwrap();
-
-            char
-        }
-        c => c,
-    })
+/// Fails with the lexer's message when the next token is not a token at all,
+/// like a malformed `\x{...}` escape.
+fn lex_error(stream: &mut Peekable<impl Iterator<Item = Token>>) -> anyhow::Result<()> {
+    match stream.peek() {
+        Some(token) if token.kind == TokenKind::Error => anyhow::bail!("{}", token.data),
+        _ => Ok(()),
+    }
 }
 
 // if let Some(token) = stream.peek() {
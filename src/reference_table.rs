@@ -0,0 +1,346 @@
+use crate::cfg::CFG;
+use crate::error::Error;
+use crate::ll_table::LLTable;
+use crate::symbol::{NonTerminal, Symbol, Terminal};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A hand-written LL(1) table in the layout of `src/re_table.txt`: a numbered
+/// list of productions followed by a grid with a header of terminals and one
+/// row per non-terminal, where `.` marks an empty cell.
+///
+/// ```text
+/// 0   S ➝ a S
+/// 1   S ➝ ε
+///
+///     a  $
+/// S   0  1
+/// ```
+///
+/// Lines containing `{` are notes and are skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReferenceTable {
+    pub productions: BTreeMap<usize, (NonTerminal, Vec<Symbol>)>,
+    pub cells: BTreeMap<NonTerminal, BTreeMap<Terminal, usize>>,
+}
+
+/// A cell where the generated table disagrees with the reference. Productions
+/// are compared by their contents, so the two may number them differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMismatch {
+    pub non_terminal: NonTerminal,
+    pub terminal: Terminal,
+    pub expected: Option<String>,
+    pub found: Option<String>,
+}
+
+impl fmt::Display for CellMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cell = |c: &Option<String>| c.clone().unwrap_or_else(|| String::from("nothing"));
+
+        write!(
+            f,
+            "[{}, {}] expected {}, found {}",
+            self.non_terminal.non_terminal(),
+            self.terminal.terminal(),
+            cell(&self.expected),
+            cell(&self.found)
+        )
+    }
+}
+
+impl ReferenceTable {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let input = read_to_string(path)
+            .with_context(|| format!("Could not read reference table {}", path.display()))?;
+
+        input
+            .parse()
+            .with_context(|| format!("Could not parse reference table {}", path.display()))
+    }
+
+    /// Compares every cell of `table` against the reference, treating rows and
+    /// columns missing from either side as empty.
+    pub fn diff(&self, cfg: &CFG, table: &LLTable<'_>) -> Vec<CellMismatch> {
+        let mut keys: BTreeSet<(NonTerminal, Terminal)> = BTreeSet::new();
+
+        for (nt, row) in &self.cells {
+            keys.extend(row.keys().map(|t| (nt.clone(), t.clone())));
+        }
+
+        for (nt, row) in &table.table {
+            keys.extend(row.keys().map(|t| ((*nt).clone(), t.clone())));
+        }
+
+        keys.into_iter()
+            .filter_map(|(nt, terminal)| {
+                let expected = self
+                    .cells
+                    .get(&nt)
+                    .and_then(|row| row.get(&terminal))
+                    .map(|idx| match self.productions.get(idx) {
+                        Some((lhs, rhs)) => (Some((lhs.clone(), rhs.clone())), *idx),
+                        None => (None, *idx),
+                    });
+
+                let found = table
+                    .table
+                    .get(&nt)
+                    .and_then(|row| row.get(&terminal).copied().flatten())
+                    .map(|idx| {
+                        let lhs = cfg.lhs(idx).cloned().unwrap_or_default();
+                        ((lhs, cfg.productions[idx].symbols().to_vec()), idx)
+                    });
+
+                let matches = match (&expected, &found) {
+                    (None, None) => true,
+                    // Without a production list the indices are all we can compare.
+                    (Some((None, e)), Some((_, f))) => e == f,
+                    (Some((Some(e), _)), Some((f, _))) => e == f,
+                    _ => false,
+                };
+
+                if matches {
+                    return None;
+                }
+
+                Some(CellMismatch {
+                    non_terminal: nt,
+                    terminal,
+                    expected: expected.map(|(production, idx)| match production {
+                        Some((lhs, rhs)) => describe(idx, &lhs, &rhs),
+                        None => idx.to_string(),
+                    }),
+                    found: found.map(|((lhs, rhs), idx)| describe(idx, &lhs, &rhs)),
+                })
+            })
+            .collect()
+    }
+}
+
+fn describe(idx: usize, lhs: &NonTerminal, rhs: &[Symbol]) -> String {
+    let rhs: Vec<&str> = rhs.iter().map(Symbol::as_str).collect();
+    format!("{} ({} -> {})", idx, lhs.non_terminal(), rhs.join(" "))
+}
+
+impl FromStr for ReferenceTable {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut reference = ReferenceTable::default();
+        let mut header: Option<Vec<Terminal>> = None;
+
+        for (i, line) in input.lines().enumerate() {
+            let error = |message: String| Error::TableParseError {
+                line: i + 1,
+                message,
+            };
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            if words.is_empty() || line.contains('{') {
+                continue;
+            }
+
+            if let Ok(idx) = words[0].parse::<usize>() {
+                let production = parse_production(&words[1..]).map_err(error)?;
+                reference.productions.insert(idx, production);
+            } else if let Some(columns) = &header {
+                let nt = match Symbol::from_parse(words[0]) {
+                    Ok(Symbol::NonTerminal(nt)) => nt,
+                    _ => {
+                        return Err(
+                            error(format!("Expected a non-terminal, found {:?}", words[0])).into(),
+                        )
+                    }
+                };
+
+                if words.len() - 1 != columns.len() {
+                    return Err(error(format!(
+                        "Expected {} cells, found {}",
+                        columns.len(),
+                        words.len() - 1
+                    ))
+                    .into());
+                }
+
+                let row = reference.cells.entry(nt).or_default();
+
+                for (terminal, cell) in columns.iter().zip(&words[1..]) {
+                    if *cell == "." {
+                        continue;
+                    }
+
+                    let idx = cell.parse().map_err(|_| {
+                        error(format!("Expected a production or '.', found {:?}", cell))
+                    })?;
+                    row.insert(terminal.clone(), idx);
+                }
+            } else {
+                let columns = words
+                    .iter()
+                    .map(|w| match Symbol::from_parse(w) {
+                        Ok(Symbol::Terminal(t)) => Ok(t),
+                        _ => Err(error(format!(
+                            "Expected a terminal in the header, found {:?}",
+                            w
+                        ))),
+                    })
+                    .collect::<Result<Vec<Terminal>, Error>>()?;
+
+                header = Some(columns);
+            }
+        }
+
+        Ok(reference)
+    }
+}
+
+/// Parses `LHS ➝ a B` where the arrow may also be `->` or `→` and lambda may
+/// be written `ε` or `λ`.
+fn parse_production(words: &[&str]) -> Result<(NonTerminal, Vec<Symbol>), String> {
+    let (lhs, arrow, rhs) = match words {
+        [lhs, arrow, rhs @ ..] => (*lhs, *arrow, rhs),
+        _ => return Err(String::from("Expected a production")),
+    };
+
+    if !["➝", "->", "→"].contains(&arrow) {
+        return Err(format!("Expected an arrow, found {:?}", arrow));
+    }
+
+    // src/re_table.txt writes one production as `.SEQ ➝ ε`, the `.` is a
+    // leftover of the grid's empty cells and not part of the name.
+    let lhs = lhs.strip_prefix('.').unwrap_or(lhs);
+
+    let lhs = match Symbol::from_parse(lhs) {
+        Ok(Symbol::NonTerminal(nt)) => nt,
+        _ => return Err(format!("Expected a non-terminal, found {:?}", lhs)),
+    };
+
+    let mut symbols = rhs
+        .iter()
+        .map(|w| match *w {
+            "ε" | "λ" => Ok(Symbol::Lambda),
+            w => Symbol::from_parse(w).map_err(|e| e.to_string()),
+        })
+        .collect::<Result<Vec<Symbol>, String>>()?;
+
+    if symbols.is_empty() {
+        symbols.push(Symbol::Lambda);
+    }
+
+    Ok((lhs, symbols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = "S -> a S | B\nB -> b | lambda";
+
+    #[test]
+    fn matching_table() {
+        let cfg: CFG = GRAMMAR.parse().unwrap();
+        let table = LLTable::from_cfg(&cfg);
+
+        // Numbered differently from the grammar, with a note and reordered columns.
+        let reference: ReferenceTable = "S {'a': 1, 'b': 2}\n\
+                                         \n\
+                                         0   B ➝ b\n\
+                                         1   S ➝ a S\n\
+                                         2   S ➝ B\n\
+                                         3   B ➝ ε\n\
+                                         \n\
+                                         \x20   b  a  $\n\
+                                         S   2  1  2\n\
+                                         B   0  .  3\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(reference.diff(&cfg, &table), vec![]);
+    }
+
+    #[test]
+    fn mismatched_cells() {
+        let cfg: CFG = GRAMMAR.parse().unwrap();
+        let table = LLTable::from_cfg(&cfg);
+
+        let reference: ReferenceTable = "0 S -> a S\n1 S -> B\n2 B -> b\n3 B -> lambda\n\
+                                         a b $ c\n\
+                                         S 0 0 . .\n\
+                                         B . 2 3 2\n"
+            .parse()
+            .unwrap();
+
+        let mismatches: Vec<String> = reference
+            .diff(&cfg, &table)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+
+        assert_eq!(
+            mismatches,
+            vec![
+                "[B, c] expected 2 (B -> b), found nothing",
+                "[S, $] expected nothing, found 1 (S -> B)",
+                "[S, b] expected 0 (S -> a S), found 1 (S -> B)",
+            ]
+        );
+    }
+
+    #[test]
+    fn regex_reference() {
+        let cfg = CFG::from_file("llre.cfg").unwrap();
+        let table = LLTable::from_cfg(&cfg);
+        let reference = ReferenceTable::from_file("src/re_table.txt").unwrap();
+
+        assert_eq!(reference.productions.len(), 17);
+        assert_eq!(reference.cells[&NonTerminal::new("ALTLIST")].len(), 3);
+        assert_eq!(
+            reference.productions[&5],
+            (NonTerminal::new("SEQ"), vec![Symbol::Lambda])
+        );
+        let mismatches: Vec<String> = reference
+            .diff(&cfg, &table)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+
+        // The reference predates `kleene` and `dot` and ends RE without `$`.
+        assert_eq!(mismatches.len(), 45);
+        for mismatch in &[
+            "[ATOMMOD, kleene] expected nothing, found 9 (ATOMMOD -> kleene)",
+            "[ATOMMOD, star] expected 9 (ATOMMOD -> star), found nothing",
+            "[NUCLEUS, dot] expected nothing, found 14 (NUCLEUS -> dot)",
+            "[RE, $] expected 0 (RE -> ALT), found 0 (RE -> ALT $)",
+            "[SEQ, $] expected 4 (SEQ -> ATOM SEQLIST), found 5 (SEQ -> lambda)",
+        ] {
+            assert!(mismatches.contains(&mismatch.to_string()), "{}", mismatch);
+        }
+
+        // The cells both tables agree on.
+        assert!(!mismatches.iter().any(|m| m.starts_with("[ALTLIST, ")));
+        assert!(!mismatches.iter().any(|m| m.starts_with("[CHARRNG, dash]")));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |input: &str| input.parse::<ReferenceTable>().unwrap_err().to_string();
+
+        assert_eq!(error("0 S => a"), "line 1: Expected an arrow, found \"=>\"");
+        assert_eq!(error("a b\nS 0"), "line 2: Expected 2 cells, found 1");
+        assert_eq!(
+            error("a\nS x"),
+            "line 2: Expected a production or '.', found \"x\""
+        );
+        assert_eq!(
+            error("a B"),
+            "line 1: Expected a terminal in the header, found \"B\""
+        );
+    }
+}
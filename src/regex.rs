@@ -0,0 +1,464 @@
+use crate::ast::{AstKind, AstNode};
+use crate::char_set::CharSet;
+use crate::error::Error;
+use crate::llre;
+use crate::parser::Parser;
+use crate::rewrite::Rewriter;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A regex in a shape that can only be valid, built from the simplified
+/// `AstNode` that `rewrite::Rewriter::regex` produces.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Regex {
+    /// Matches only the empty string.
+    Empty,
+    Char(char),
+    /// Any one of the characters.
+    Set(CharSet),
+    /// Any character of the alphabet, written `.`.
+    Any,
+    Concat(Vec<Regex>),
+    /// Any one of the regexes, or nothing at all when there are none.
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+    /// Between `min` and `max` repetitions, or at least `min` without a `max`.
+    Repeat {
+        regex: Box<Regex>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Regex {
+    pub fn star(regex: Regex) -> Self {
+        Regex::Star(Box::new(regex))
+    }
+
+    pub fn plus(regex: Regex) -> Self {
+        Regex::Plus(Box::new(regex))
+    }
+
+    pub fn optional(regex: Regex) -> Self {
+        Regex::Optional(Box::new(regex))
+    }
+
+    /// Whether the regex matches the empty string.
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Regex::Empty | Regex::Star(_) | Regex::Optional(_) => true,
+            Regex::Char(_) | Regex::Set(_) | Regex::Any => false,
+            Regex::Concat(regexes) => regexes.iter().all(Regex::is_nullable),
+            Regex::Alt(regexes) => regexes.iter().any(Regex::is_nullable),
+            Regex::Plus(regex) => regex.is_nullable(),
+            Regex::Repeat { regex, min, .. } => *min == 0 || regex.is_nullable(),
+        }
+    }
+
+    /// Whether the regex uses `.`, which only means something over an alphabet.
+    pub fn contains_any(&self) -> bool {
+        match self {
+            Regex::Any => true,
+            Regex::Empty | Regex::Char(_) | Regex::Set(_) => false,
+            Regex::Concat(regexes) | Regex::Alt(regexes) => regexes.iter().any(Regex::contains_any),
+            Regex::Star(regex) | Regex::Plus(regex) | Regex::Optional(regex) => {
+                regex.contains_any()
+            }
+            Regex::Repeat { regex, .. } => regex.contains_any(),
+        }
+    }
+
+    /// The characters the regex names, leaving out the ones `.` stands for.
+    pub fn chars(&self) -> CharSet {
+        match self {
+            Regex::Empty | Regex::Any => CharSet::new(),
+            Regex::Char(c) => CharSet::single(*c),
+            Regex::Set(chars) => chars.clone(),
+            Regex::Concat(regexes) | Regex::Alt(regexes) => regexes
+                .iter()
+                .fold(CharSet::new(), |chars, regex| chars.union(&regex.chars())),
+            Regex::Star(regex) | Regex::Plus(regex) | Regex::Optional(regex) => regex.chars(),
+            Regex::Repeat { regex, .. } => regex.chars(),
+        }
+    }
+}
+
+/// How tightly a part of a regex binds, loosest first. A part is wrapped in
+/// parentheses when its context needs it to bind tighter than it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Alt,
+    Concat,
+    Postfix,
+    Atom,
+}
+
+/// Prints the regex in the syntax of `silly_lex` with as few parentheses as
+/// parsing it back needs. `Set`, `Optional` and `Repeat` have no syntax of
+/// their own and print as the alternatives or sequence they stand for.
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, Precedence::Alt)
+    }
+}
+
+impl Regex {
+    fn precedence(&self) -> Precedence {
+        match self {
+            Regex::Empty | Regex::Char(_) | Regex::Any => Precedence::Atom,
+            Regex::Set(chars) if chars.ranges().len() <= 1 => Precedence::Atom,
+            Regex::Optional(_) => Precedence::Atom,
+            Regex::Star(_) | Regex::Plus(_) => Precedence::Postfix,
+            Regex::Concat(regexes) if regexes.len() == 1 => regexes[0].precedence(),
+            Regex::Concat(_) | Regex::Repeat { .. } => Precedence::Concat,
+            Regex::Alt(regexes) if regexes.len() == 1 => regexes[0].precedence(),
+            Regex::Alt(regexes) if regexes.is_empty() => Precedence::Atom,
+            Regex::Set(_) | Regex::Alt(_) => Precedence::Alt,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, context: Precedence) -> fmt::Result {
+        if self.precedence() < context {
+            write!(f, "(")?;
+            self.write(f, Precedence::Alt)?;
+            return write!(f, ")");
+        }
+
+        match self {
+            // Only alternatives can leave out the empty string, `a|`.
+            Regex::Empty if context == Precedence::Alt => Ok(()),
+            Regex::Empty => write!(f, "()"),
+            Regex::Char(c) => write!(f, "{}", escape(*c)),
+            Regex::Any => write!(f, "."),
+            Regex::Set(chars) => {
+                let ranges = chars.ranges();
+
+                if ranges.is_empty() {
+                    return Regex::Alt(vec![]).write(f, context);
+                }
+
+                for (i, &(start, end)) in ranges.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+
+                    match (start, end) {
+                        (start, end) if start == end => write!(f, "{}", escape(start))?,
+                        (start, end) => write!(f, "{}-{}", escape(start), escape(end))?,
+                    }
+                }
+
+                Ok(())
+            }
+            Regex::Concat(regexes) => match regexes.as_slice() {
+                [] => Regex::Empty.write(f, context),
+                [regex] => regex.write(f, context),
+                regexes => {
+                    for regex in regexes {
+                        // A nested sequence keeps its parentheses so that it
+                        // parses back to the same shape.
+                        match regex {
+                            Regex::Concat(inner) if inner.len() > 1 => {
+                                regex.write(f, Precedence::Postfix)?
+                            }
+                            regex => regex.write(f, Precedence::Concat)?,
+                        }
+                    }
+
+                    Ok(())
+                }
+            },
+            // A reversed range is the only syntax that matches nothing.
+            Regex::Alt(regexes) if regexes.is_empty() => write!(f, "b-a"),
+            Regex::Alt(regexes) if regexes.len() == 1 => regexes[0].write(f, context),
+            Regex::Alt(regexes) => {
+                for (i, regex) in regexes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+
+                    match regex {
+                        Regex::Alt(inner) if inner.len() > 1 => {
+                            regex.write(f, Precedence::Concat)?
+                        }
+                        regex => regex.write(f, Precedence::Alt)?,
+                    }
+                }
+
+                Ok(())
+            }
+            Regex::Star(regex) => {
+                regex.write(f, Precedence::Atom)?;
+                write!(f, "*")
+            }
+            Regex::Plus(regex) => {
+                regex.write(f, Precedence::Atom)?;
+                write!(f, "+")
+            }
+            Regex::Optional(regex) => {
+                write!(f, "(")?;
+                Regex::Alt(vec![(**regex).clone(), Regex::Empty]).write(f, Precedence::Alt)?;
+                write!(f, ")")
+            }
+            Regex::Repeat { regex, min, max } => {
+                let mut regexes = vec![(**regex).clone(); *min];
+
+                match max {
+                    Some(max) => regexes.extend(std::iter::repeat_n(
+                        Regex::Optional(regex.clone()),
+                        max.saturating_sub(*min),
+                    )),
+                    None => regexes.push(Regex::Star(regex.clone())),
+                }
+
+                Regex::Concat(regexes).write(f, context)
+            }
+        }
+    }
+}
+
+/// Escapes the characters `silly_lex` would read as an operator, and control
+/// characters that would not survive a `.lut` file.
+fn escape(c: char) -> String {
+    match c {
+        ' ' => String::from("\\s"),
+        '\n' => String::from("\\n"),
+        '\\' | '*' | '+' | '(' | ')' | '.' | '-' | '|' => format!("\\{}", c),
+        c if c.is_control() => format!("\\x{{{:X}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+impl TryFrom<&AstNode> for Regex {
+    type Error = Error;
+
+    fn try_from(node: &AstNode) -> Result<Self, Error> {
+        let children = || {
+            node.children
+                .iter()
+                .map(Regex::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let child = || match node.children.as_slice() {
+            [child] => Regex::try_from(child).map(Box::new),
+            children => Err(Error::AstError(format!(
+                "{:?} has {} children instead of one",
+                node.kind,
+                children.len()
+            ))),
+        };
+
+        let leaf = |regex| match node.children.len() {
+            0 => Ok(regex),
+            n => Err(Error::AstError(format!(
+                "{:?} is a leaf but has {} children",
+                node.kind, n
+            ))),
+        };
+
+        match node.kind {
+            AstKind::Lambda => leaf(Regex::Empty),
+            AstKind::Char(c) => leaf(Regex::Char(c)),
+            AstKind::Dot => leaf(Regex::Any),
+            AstKind::Seq => Ok(Regex::Concat(children()?)),
+            AstKind::Alt => Ok(Regex::Alt(children()?)),
+            AstKind::CharRng => match node.children.as_slice() {
+                [start, end] if start.children.is_empty() && end.children.is_empty() => {
+                    match (start.kind, end.kind) {
+                        (AstKind::Char(start), AstKind::Char(end)) => {
+                            Ok(Regex::Set(CharSet::range(start, end)))
+                        }
+                        _ => Err(Error::AstError(String::from(
+                            "CharRng needs a start and an end character",
+                        ))),
+                    }
+                }
+                _ => Err(Error::AstError(String::from(
+                    "CharRng needs a start and an end character",
+                ))),
+            },
+            AstKind::Kleene => Ok(Regex::Star(child()?)),
+            AstKind::Plus => Ok(Regex::Plus(child()?)),
+            kind => Err(Error::AstError(format!(
+                "{:?} is part of the parse tree, not of a simplified AST",
+                kind
+            ))),
+        }
+    }
+}
+
+/// Parses a regex in the syntax of `silly_lex` with the built-in llre grammar.
+impl FromStr for Regex {
+    type Err = anyhow::Error;
+
+    fn from_str(regex: &str) -> anyhow::Result<Self> {
+        let parser = Parser::new(&llre::GRAMMAR, &llre::TABLE);
+        let mut lexer = silly_lex::Lexer::new(regex).iter().peekable();
+        let ast = Rewriter::regex().parse(&parser, &mut lexer)?;
+
+        Ok(Regex::try_from(&ast)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(kind: AstKind, children: Vec<AstNode>) -> AstNode {
+        AstNode { kind, children }
+    }
+
+    #[test]
+    fn from_ast() {
+        let c = |c| AstNode::new(AstKind::Char(c));
+        let ast = node(
+            AstKind::Alt,
+            vec![
+                node(
+                    AstKind::Seq,
+                    vec![c('a'), node(AstKind::Kleene, vec![c('b')])],
+                ),
+                AstNode::new(AstKind::Dot),
+                AstNode::new(AstKind::Lambda),
+            ],
+        );
+
+        assert_eq!(
+            Regex::try_from(&ast).unwrap(),
+            Regex::Alt(vec![
+                Regex::Concat(vec![Regex::Char('a'), Regex::star(Regex::Char('b'))]),
+                Regex::Any,
+                Regex::Empty,
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_shapes() {
+        let error = |ast: &AstNode| Regex::try_from(ast).unwrap_err().to_string();
+        let c = AstNode::new(AstKind::Char('a'));
+
+        assert_eq!(
+            error(&node(AstKind::Kleene, vec![c.clone(), c.clone()])),
+            "Invalid regex AST: Kleene has 2 children instead of one"
+        );
+        assert_eq!(
+            error(&node(AstKind::Char('b'), vec![c.clone()])),
+            "Invalid regex AST: Char('b') is a leaf but has 1 children"
+        );
+        assert_eq!(
+            error(&node(AstKind::Seq, vec![node(AstKind::AltList, vec![c])])),
+            "Invalid regex AST: AltList is part of the parse tree, not of a simplified AST"
+        );
+    }
+
+    #[test]
+    fn nullable() {
+        let a = || Regex::Char('a');
+
+        assert!(Regex::Concat(vec![]).is_nullable());
+        assert!(!Regex::Alt(vec![]).is_nullable());
+        assert!(Regex::Concat(vec![Regex::star(a()), Regex::optional(a())]).is_nullable());
+        assert!(!Regex::plus(a()).is_nullable());
+        assert!(Regex::Repeat {
+            regex: Box::new(a()),
+            min: 0,
+            max: Some(2)
+        }
+        .is_nullable());
+    }
+
+    #[test]
+    fn printing() {
+        let print = |regex: &str| regex.parse::<Regex>().unwrap().to_string();
+
+        assert_eq!(print("(a)"), "a");
+        assert_eq!(print("((ab)c)|(d|e)"), "(ab)c|(d|e)");
+        assert_eq!(print("(a|b)*c+"), "(a|b)*c+");
+        assert_eq!(print("(ab)*"), "(ab)*");
+        assert_eq!(print("a()|"), "a()|");
+        assert_eq!(print("()*"), "()*");
+        assert_eq!(print("\\s\\n\\*\\\\"), "\\s\\n\\*\\\\");
+        assert_eq!(print("b-a"), "b-a");
+        assert_eq!(print("\\x{1F600}\\x{9}\\x{78}"), "😀\\x{9}x");
+
+        let set = |chars: &str| Regex::Set(chars.chars().collect());
+        assert_eq!(set("abcz").to_string(), "a-c|z");
+        assert_eq!(Regex::star(set("abc")).to_string(), "a-c*");
+        assert_eq!(Regex::star(set("az")).to_string(), "(a|z)*");
+        assert_eq!(Regex::optional(Regex::Char('a')).to_string(), "(a|)");
+        assert_eq!(
+            Regex::Repeat {
+                regex: Box::new(Regex::Char('a')),
+                min: 1,
+                max: Some(2)
+            }
+            .to_string(),
+            "a(a|)"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for regex in &[
+            "",
+            "a",
+            "abc",
+            "a|b|",
+            "|a",
+            "a*b+",
+            "(a|b)(c|d)",
+            "((a|b)|c)",
+            "a(bc)d",
+            "(a*)*",
+            "(a+)+",
+            "a-d.e",
+            "(a-c)*",
+            "(\\(|\\))*\\s",
+            "\\-\\|\\.\\+",
+            "0-9+(\\.0-9*)",
+            "((()))",
+            "(|a)b",
+            "x+|xyz",
+            "α-ω*\\x{1F600}",
+            "\\x{7}\\x{78}",
+        ] {
+            let parsed: Regex = regex.parse().unwrap();
+            let printed = parsed.to_string();
+            let reparsed: Regex = printed.parse().unwrap();
+            assert_eq!(reparsed, parsed, "{:?} printed as {:?}", regex, printed);
+        }
+    }
+
+    #[test]
+    fn malformed_escapes() {
+        let error = "a\\x{zz}".parse::<Regex>().unwrap_err();
+        assert_eq!(error.to_string(), "\\x{zz} is not a Unicode scalar value");
+
+        let error = "a\\x{110000}b".parse::<Regex>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "\\x{110000} is not a Unicode scalar value"
+        );
+
+        let error = "\\x{41".parse::<Regex>().unwrap_err();
+        assert_eq!(error.to_string(), "Unterminated escape \\x{41");
+    }
+
+    #[test]
+    fn contains_any() {
+        for (regex, expected) in [
+            ("a.b", true),
+            ("(a|.*)c", true),
+            ("a-z+", false),
+            ("\\.", false),
+        ] {
+            let regex: Regex = regex.parse().unwrap();
+            assert_eq!(regex.contains_any(), expected, "{:?}", regex);
+        }
+    }
+}
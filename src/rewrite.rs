@@ -0,0 +1,328 @@
+use crate::ast::{AstKind, AstNode};
+use crate::parser::{get_char, Actions, Parser};
+use crate::symbol::Terminal;
+use anyhow::{bail, Result};
+use silly_lex::Token;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::Peekable;
+
+pub type Postfix = fn(AstNode) -> Result<AstNode>;
+pub type Infix = fn(AstNode, AstNode) -> Result<AstNode>;
+
+/// Maps the parse tree of a grammar to an AST without hand-written code for
+/// each non-terminal. Every production is flattened into a list of pieces:
+///
+/// - productions whose `=> label` is registered with `node` become a node of
+///   that kind once their operators are applied, or their only child, or
+///   `Lambda` when empty
+/// - every other production, e.g. the `*LIST` tails, is spliced into its
+///   parent
+/// - lambda and punctuation terminals are dropped
+/// - `postfix` and `infix` terminals combine the nodes next to them, in the
+///   first production up the tree that has those nodes
+/// - any other terminal becomes a leaf: `char` a `Char`, the rest the kind
+///   named by `AstKind::from_str`
+///
+/// Adding an operator to a grammar then only takes a rule here.
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    nodes: BTreeMap<String, AstKind>,
+    punctuation: BTreeSet<Terminal>,
+    postfix: BTreeMap<Terminal, Postfix>,
+    infix: BTreeMap<Terminal, Infix>,
+}
+
+/// A flattened part of the parse tree, the `Actions::Value` of a `Rewriter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    Node(AstNode),
+    Postfix(Terminal),
+    Infix(Terminal),
+}
+
+impl Rewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rules for `llre.cfg`.
+    pub fn regex() -> Self {
+        Self::new()
+            .node("alt", AstKind::Alt)
+            .node("seq", AstKind::Seq)
+            .punctuation("pipe")
+            .punctuation("open")
+            .punctuation("close")
+            .postfix("kleene", |a| Ok(kleene(a)))
+            .postfix("plus", |a| {
+                Ok(AstNode {
+                    kind: AstKind::Plus,
+                    children: vec![a],
+                })
+            })
+            .infix("dash", |start, end| {
+                Ok(AstNode {
+                    kind: AstKind::CharRng,
+                    children: vec![start, end],
+                })
+            })
+    }
+
+    pub fn node(mut self, label: &str, kind: AstKind) -> Self {
+        self.nodes.insert(label.to_string(), kind);
+        self
+    }
+
+    pub fn punctuation(mut self, terminal: &str) -> Self {
+        self.punctuation.insert(Terminal::new(terminal));
+        self
+    }
+
+    pub fn postfix(mut self, terminal: &str, apply: Postfix) -> Self {
+        self.postfix.insert(Terminal::new(terminal), apply);
+        self
+    }
+
+    pub fn infix(mut self, terminal: &str, apply: Infix) -> Self {
+        self.infix.insert(Terminal::new(terminal), apply);
+        self
+    }
+
+    /// Parses `stream` with `parser` and rewrites the tree into a single node.
+    pub fn parse(
+        &mut self,
+        parser: &Parser<'_, '_>,
+        stream: &mut Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<AstNode> {
+        let pieces = parser.parse_with(stream, self)?;
+        let mut nodes = self.nodes(pieces)?;
+
+        match nodes.len() {
+            0 => Ok(AstNode::new(AstKind::Lambda)),
+            1 => Ok(nodes.pop().unwrap()),
+            n => bail!("The start symbol rewrote to {} nodes instead of one", n),
+        }
+    }
+
+    /// Applies every operator in `pieces` that has its operands there, left
+    /// to right. The rest wait for the production above, so an operator binds
+    /// to what the grammar put next to it: `a-c*` is a range and then a star.
+    fn apply(&self, pieces: Vec<Piece>) -> Result<Vec<Piece>> {
+        let mut applied: Vec<Piece> = Vec::new();
+
+        for piece in pieces {
+            match (piece, applied.as_slice()) {
+                (Piece::Postfix(t), [.., Piece::Node(_)]) => {
+                    if let Some(Piece::Node(node)) = applied.pop() {
+                        applied.push(Piece::Node(self.postfix[&t](node)?));
+                    }
+                }
+                (Piece::Node(right), [.., Piece::Node(_), Piece::Infix(_)]) => {
+                    if let (Some(Piece::Infix(t)), Some(Piece::Node(left))) =
+                        (applied.pop(), applied.pop())
+                    {
+                        applied.push(Piece::Node(self.infix[&t](left, right)?));
+                    }
+                }
+                (piece, _) => applied.push(piece),
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// The nodes of `pieces` once all of their operators are applied.
+    fn nodes(&self, pieces: Vec<Piece>) -> Result<Vec<AstNode>> {
+        self.apply(pieces)?
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Node(node) => Ok(node),
+                Piece::Postfix(t) | Piece::Infix(t) => {
+                    bail!("{} is missing an operand", t.terminal())
+                }
+            })
+            .collect()
+    }
+}
+
+impl Actions for Rewriter {
+    type Value = Vec<Piece>;
+
+    fn token(&mut self, terminal: &Terminal, token: &Token) -> Result<Vec<Piece>> {
+        if self.punctuation.contains(terminal) {
+            return Ok(vec![]);
+        } else if self.postfix.contains_key(terminal) {
+            return Ok(vec![Piece::Postfix(terminal.clone())]);
+        } else if self.infix.contains_key(terminal) {
+            return Ok(vec![Piece::Infix(terminal.clone())]);
+        }
+
+        let kind = match terminal.terminal() {
+            "char" => AstKind::Char(get_char(&token.data)),
+            name => match AstKind::from_str(name) {
+                Some(kind) => kind,
+                None => bail!("No node kind for the terminal {}", name),
+            },
+        };
+
+        Ok(vec![Piece::Node(AstNode::new(kind))])
+    }
+
+    fn reduce(&mut self, label: &str, children: Vec<Vec<Piece>>) -> Result<Vec<Piece>> {
+        let pieces = children.into_iter().flatten().collect();
+
+        let kind = match self.nodes.get(label) {
+            Some(kind) => *kind,
+            None => return self.apply(pieces),
+        };
+
+        let mut nodes = self.nodes(pieces)?;
+
+        let node = match nodes.len() {
+            0 => AstNode::new(AstKind::Lambda),
+            1 => nodes.pop().unwrap(),
+            _ => AstNode {
+                kind,
+                children: nodes,
+            },
+        };
+
+        Ok(vec![Piece::Node(node)])
+    }
+}
+
+fn kleene(node: AstNode) -> AstNode {
+    AstNode {
+        kind: AstKind::Kleene,
+        children: vec![node],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::CFG;
+    use crate::ll_table::LLTable;
+    use crate::llre;
+
+    fn lex(input: &str) -> Peekable<impl Iterator<Item = Token> + '_> {
+        silly_lex::Lexer::new(input).iter().peekable()
+    }
+
+    fn build(regex: &str) -> Result<AstNode> {
+        let parser = Parser::new(&llre::GRAMMAR, &llre::TABLE);
+        Rewriter::regex().parse(&parser, &mut lex(regex))
+    }
+
+    #[test]
+    fn regex_ast() {
+        let node = |kind, children| AstNode { kind, children };
+        let c = |c| AstNode::new(AstKind::Char(c));
+
+        assert_eq!(build("a").unwrap(), c('a'));
+        assert_eq!(build("").unwrap(), AstNode::new(AstKind::Lambda));
+        assert_eq!(build("((a))").unwrap(), c('a'));
+        assert_eq!(
+            build("a-c*").unwrap(),
+            node(
+                AstKind::Kleene,
+                vec![node(AstKind::CharRng, vec![c('a'), c('c')])]
+            )
+        );
+        assert_eq!(
+            build("a|bc").unwrap(),
+            node(
+                AstKind::Alt,
+                vec![c('a'), node(AstKind::Seq, vec![c('b'), c('c')])]
+            )
+        );
+        assert_eq!(
+            build("(a-c)+.").unwrap(),
+            node(
+                AstKind::Seq,
+                vec![
+                    node(
+                        AstKind::Plus,
+                        vec![node(AstKind::CharRng, vec![c('a'), c('c')])]
+                    ),
+                    AstNode::new(AstKind::Dot),
+                ]
+            )
+        );
+        assert_eq!(
+            build("a*|").unwrap(),
+            node(
+                AstKind::Alt,
+                vec![
+                    node(AstKind::Kleene, vec![c('a')]),
+                    AstNode::new(AstKind::Lambda)
+                ]
+            )
+        );
+        assert_eq!(
+            build("|a").unwrap(),
+            node(AstKind::Alt, vec![AstNode::new(AstKind::Lambda), c('a')])
+        );
+
+        assert!(build("a(").is_err());
+        assert!(build(".-").is_err());
+    }
+
+    #[test]
+    fn new_operator() {
+        // A grammar that adds `plus` only needs one rule for it.
+        let cfg: CFG = "RE -> SEQ $\n\
+                            SEQ -> ATOM SEQLIST => seq | lambda => seq\n\
+                            SEQLIST -> ATOM SEQLIST => seq_list | lambda => lambda\n\
+                            ATOM -> char MOD => atom\n\
+                            MOD -> kleene | plus | lambda => lambda"
+            .parse()
+            .unwrap();
+        let table = LLTable::from_cfg(&cfg);
+        let parser = Parser::new(&cfg, &table);
+
+        let rewriter = Rewriter::new()
+            .node("seq", AstKind::Seq)
+            .postfix("kleene", |a| Ok(kleene(a)))
+            .postfix("plus", |a| {
+                Ok(AstNode {
+                    kind: AstKind::Plus,
+                    children: vec![a],
+                })
+            });
+
+        let node = |kind, children| AstNode { kind, children };
+        let c = |c| AstNode::new(AstKind::Char(c));
+
+        assert_eq!(
+            rewriter.clone().parse(&parser, &mut lex("ab+c*")).unwrap(),
+            node(
+                AstKind::Seq,
+                vec![
+                    c('a'),
+                    node(AstKind::Plus, vec![c('b')]),
+                    node(AstKind::Kleene, vec![c('c')])
+                ]
+            )
+        );
+        assert_eq!(
+            rewriter.clone().parse(&parser, &mut lex("a")).unwrap(),
+            c('a')
+        );
+        assert!(rewriter.clone().parse(&parser, &mut lex("a(")).is_err());
+    }
+
+    #[test]
+    fn unlabelled_production() {
+        // Only a label says how to combine more than one value.
+        let cfg: CFG = "RE -> PAIR $\nPAIR -> char char".parse().unwrap();
+        let table = LLTable::from_cfg(&cfg);
+        let parser = Parser::new(&cfg, &table);
+
+        let error = Rewriter::new().parse(&parser, &mut lex("ab")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "A production of PAIR has 2 values but no action to combine them"
+        );
+    }
+}
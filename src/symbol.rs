@@ -7,7 +7,7 @@ use std::fmt;
 
 lazy_static! {
     static ref SYMBOL: Regex =
-        Regex::new(r#"(?P<lambda>lambda)|(?P<terminal>[a-z][a-z_]*)|(?P<nonterminal>[A-Z][a-zA-Z_]*)|(?P<dollar>\$)"#)
+        Regex::new(r#"(?P<lambda>lambda)|(?P<terminal>[a-z][a-z_]*)|(?P<nonterminal>[A-Z][a-zA-Z0-9_]*)|(?P<dollar>\$)"#)
             .unwrap();
 }
 
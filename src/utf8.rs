@@ -0,0 +1,192 @@
+use crate::char_set::CharSet;
+use crate::optimize::rewrite;
+use crate::regex::Regex;
+use std::collections::BTreeSet;
+
+/// A run of byte ranges that matches the UTF-8 encoding of a range of
+/// characters, one range per byte.
+pub type Utf8Sequence = Vec<(u8, u8)>;
+
+/// The largest code point encoded with 1, 2 and 3 bytes.
+const LENGTH_BOUNDS: [u32; 3] = [0x7F, 0x7FF, 0xFFFF];
+
+/// The 256 symbols of a byte automaton. Byte `b` is the character `b as char`,
+/// so `0x80` to `0xFF` stand for bytes and not for `U+0080` to `U+00FF`.
+pub fn byte_alphabet() -> BTreeSet<char> {
+    (0..=255u8).map(char::from).collect()
+}
+
+/// Splits `chars` into sequences of byte ranges whose UTF-8 encodings cover
+/// exactly its characters, in order. A sequence of ranges matches every
+/// combination of its bytes, so a range is split wherever that would also
+/// match bytes outside of it.
+pub fn sequences(chars: &CharSet) -> Vec<Utf8Sequence> {
+    let mut sequences = Vec::new();
+
+    for &(start, end) in chars.ranges() {
+        let mut stack = vec![(start as u32, end as u32)];
+
+        'ranges: while let Some((start, end)) = stack.pop() {
+            // The later half is pushed first so the earlier one comes out first.
+            if start < 0xD800 && end > 0xDFFF {
+                stack.push((0xE000, end));
+                stack.push((start, 0xD7FF));
+                continue;
+            }
+
+            for &bound in &LENGTH_BOUNDS {
+                if start <= bound && bound < end {
+                    stack.push((bound + 1, end));
+                    stack.push((start, bound));
+                    continue 'ranges;
+                }
+            }
+
+            // Split until every continuation byte covers all of 0x80-0xBF or
+            // the leading bytes agree.
+            for i in 1..4 {
+                let max = (1 << (6 * i)) - 1;
+
+                if start & !max != end & !max {
+                    if start & max != 0 {
+                        stack.push(((start | max) + 1, end));
+                        stack.push((start, start | max));
+                        continue 'ranges;
+                    }
+
+                    if end & max != max {
+                        stack.push((end & !max, end));
+                        stack.push((start, (end & !max) - 1));
+                        continue 'ranges;
+                    }
+                }
+            }
+
+            let (mut first, mut last) = ([0; 4], [0; 4]);
+            let first = encode(start, &mut first);
+            let last = encode(end, &mut last);
+
+            sequences.push(first.iter().copied().zip(last.iter().copied()).collect());
+        }
+    }
+
+    sequences
+}
+
+fn encode(code_point: u32, buffer: &mut [u8; 4]) -> &[u8] {
+    // Both ends of a range are scalar values, the surrogates were split off.
+    let c = std::char::from_u32(code_point).unwrap();
+    c.encode_utf8(buffer).as_bytes()
+}
+
+/// Rewrites `regex` to match the UTF-8 encoding of what it matched, over the
+/// symbols of `byte_alphabet`. `.` stands for any character of `alphabet`.
+pub fn to_bytes(regex: &Regex, alphabet: &BTreeSet<char>) -> Regex {
+    rewrite(regex.clone(), &|regex| match regex {
+        Regex::Char(c) => {
+            let mut buffer = [0; 4];
+            let bytes = c.encode_utf8(&mut buffer).bytes();
+            Regex::Concat(bytes.map(|b| Regex::Char(char::from(b))).collect())
+        }
+        Regex::Set(chars) => set_to_bytes(&chars),
+        Regex::Any => set_to_bytes(&alphabet.iter().collect()),
+        regex => regex,
+    })
+}
+
+fn set_to_bytes(chars: &CharSet) -> Regex {
+    let byte = |(start, end): (u8, u8)| match start == end {
+        true => Regex::Char(char::from(start)),
+        false => Regex::Set(CharSet::range(char::from(start), char::from(end))),
+    };
+
+    Regex::Alt(
+        sequences(chars)
+            .into_iter()
+            .map(|sequence| Regex::Concat(sequence.into_iter().map(byte).collect()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::Dfa;
+    use crate::optimize::optimize;
+
+    #[test]
+    fn utf8_sequences() {
+        assert_eq!(
+            sequences(&CharSet::range('a', 'z')),
+            vec![vec![(b'a', b'z')]]
+        );
+        assert_eq!(
+            sequences(&CharSet::all()),
+            vec![
+                vec![(0x00, 0x7F)],
+                vec![(0xC2, 0xDF), (0x80, 0xBF)],
+                vec![(0xE0, 0xE0), (0xA0, 0xBF), (0x80, 0xBF)],
+                vec![(0xE1, 0xEC), (0x80, 0xBF), (0x80, 0xBF)],
+                vec![(0xED, 0xED), (0x80, 0x9F), (0x80, 0xBF)],
+                vec![(0xEE, 0xEF), (0x80, 0xBF), (0x80, 0xBF)],
+                vec![(0xF0, 0xF0), (0x90, 0xBF), (0x80, 0xBF), (0x80, 0xBF)],
+                vec![(0xF1, 0xF3), (0x80, 0xBF), (0x80, 0xBF), (0x80, 0xBF)],
+                vec![(0xF4, 0xF4), (0x80, 0x8F), (0x80, 0xBF), (0x80, 0xBF)],
+            ]
+        );
+        assert_eq!(
+            sequences(&CharSet::range('α', 'ω')),
+            vec![
+                vec![(0xCE, 0xCE), (0xB1, 0xBF)],
+                vec![(0xCF, 0xCF), (0x80, 0x89)]
+            ]
+        );
+    }
+
+    #[test]
+    fn byte_dfa() {
+        let sets = [
+            CharSet::range('α', 'ω'),
+            CharSet::range('\u{7F}', '\u{10000}'),
+            CharSet::range('\u{D000}', '\u{E100}'),
+            CharSet::range('\u{FFF}', '\u{10FFFF}').complement(),
+        ];
+        let samples =
+            "a\u{7F}\u{80}αλωϊ\u{FFF}\u{1000}\u{D7FF}\u{E000}\u{FFFF}\u{10000}😀\u{10FFFF}";
+
+        for set in &sets {
+            let dfa = Dfa::from_regex(
+                &optimize(to_bytes(&Regex::Set(set.clone()), &BTreeSet::new())),
+                &byte_alphabet(),
+            );
+
+            for c in samples.chars() {
+                let mut buffer = [0; 4];
+                let bytes = c.encode_utf8(&mut buffer).as_bytes();
+                assert_eq!(dfa.accepts_bytes(bytes), set.contains(c), "{} {:?}", set, c);
+            }
+
+            assert!(!dfa.accepts_bytes(b"\xCE") && !dfa.accepts_bytes(b"\xED\xA0\x80"));
+        }
+    }
+
+    #[test]
+    fn byte_regex() {
+        let word = Regex::Concat(vec![
+            Regex::plus(Regex::Set(CharSet::range('α', 'ω'))),
+            Regex::Char('😀'),
+            Regex::star(Regex::Any),
+        ]);
+        let dfa = Dfa::from_regex(
+            &optimize(to_bytes(&word, &"ab".chars().collect())),
+            &byte_alphabet(),
+        );
+
+        assert!(dfa.accepts_bytes("λμ😀".as_bytes()) && dfa.accepts_bytes("α😀ab".as_bytes()));
+        assert!(!dfa.accepts_bytes("😀".as_bytes()) && !dfa.accepts_bytes("α😀c".as_bytes()));
+        assert!(dfa.states.iter().all(|state| state
+            .transitions
+            .iter()
+            .all(|(label, _)| label.ranges().iter().all(|&(_, end)| end <= '\u{FF}'))));
+    }
+}